@@ -0,0 +1,160 @@
+//! Compares permission checks made behind the store's lock with checks made
+//! against the published snapshot, while a writer keeps changing and saving
+//! the store the way commands do.
+//!
+//! Run with `cargo bench --bench concurrent_checks`.
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use seed::node::PermissionContext;
+use seed::storage::TomlStorage;
+use seed::store::{Group, PermissionStore};
+
+const GROUPS: usize = 20;
+const PLAYERS: u128 = 1000;
+const READERS: usize = 4;
+const RUN_FOR: Duration = Duration::from_secs(3);
+const NODES: [&str; 5] = [
+    "bench:group3.node7",
+    "bench:group12.wild.deep.node",
+    "bench:group0.node0",
+    "minecraft:command.help",
+    "unknown:node",
+];
+
+/// A chain of groups, each inheriting from the previous one, with a mix of
+/// exact, wildcard and negated entries. Players are spread across them.
+fn build_store() -> PermissionStore {
+    let data_folder = std::env::temp_dir().join("seed-bench");
+    std::fs::create_dir_all(&data_folder).expect("create bench data folder");
+    let groups = (0..GROUPS)
+        .map(|i| {
+            let permissions = (0..50)
+                .map(|j| format!("bench:group{i}.node{j}"))
+                .chain([
+                    format!("bench:group{i}.wild.*"),
+                    format!("-bench:group{}.node0", i + 1),
+                ])
+                .collect();
+            let inheritance = match i {
+                0 => Vec::new(),
+                1 => vec!["default".to_string()],
+                _ => vec![format!("group{}", i - 1)],
+            };
+            let name = if i == 0 {
+                "default".to_string()
+            } else {
+                format!("group{i}")
+            };
+            let group = Group {
+                permissions,
+                inheritance,
+                weight: i as i32,
+                ..Default::default()
+            };
+            (name, group)
+        })
+        .collect();
+    let storage = Arc::new(TomlStorage::new(data_folder.clone()));
+    let mut store =
+        PermissionStore::new(data_folder, storage, groups, HashMap::new(), HashMap::new());
+    for n in 0..PLAYERS {
+        let uuid = Uuid::from_u128(n);
+        let pd = store.get_or_create_player(uuid, &format!("player{n}"));
+        pd.groups = vec![format!("group{}", 1 + n as usize % (GROUPS - 1))];
+        pd.extra_permissions.push(format!("bench:player{n}.*"));
+        store.refresh_player(uuid);
+    }
+    store
+}
+
+/// Runs `READERS` threads of checks against one writer for `RUN_FOR`, then
+/// prints throughput and the slowest single check.
+fn run(
+    name: &str,
+    store: &RwLock<PermissionStore>,
+    check: impl Fn(&Uuid, &str) -> Option<bool> + Sync,
+) {
+    let stop = AtomicBool::new(false);
+    let (checks, worst, writes) = thread::scope(|scope| {
+        let writer = scope.spawn(|| {
+            let uuid = Uuid::from_u128(0);
+            let mut writes = 0u64;
+            while !stop.load(Ordering::Relaxed) {
+                let mut store = store.blocking_write();
+                let pd = store.get_or_create_player(uuid, "player0");
+                if pd.denied_permissions.pop().is_none() {
+                    pd.denied_permissions.push("bench:group1.node1".to_string());
+                }
+                store.refresh_player(uuid);
+                store.save().expect("save bench store");
+                drop(store);
+                writes += 1;
+                thread::sleep(Duration::from_millis(1));
+            }
+            writes
+        });
+        let readers: Vec<_> = (0..READERS)
+            .map(|reader| {
+                let (stop, check) = (&stop, &check);
+                scope.spawn(move || {
+                    let mut checks = 0u64;
+                    let mut worst = Duration::ZERO;
+                    let mut i = reader;
+                    while !stop.load(Ordering::Relaxed) {
+                        let uuid = Uuid::from_u128(i as u128 % PLAYERS);
+                        let start = Instant::now();
+                        black_box(check(&uuid, NODES[i % NODES.len()]));
+                        worst = worst.max(start.elapsed());
+                        checks += 1;
+                        i += 1;
+                    }
+                    (checks, worst)
+                })
+            })
+            .collect();
+        thread::sleep(RUN_FOR);
+        stop.store(true, Ordering::Relaxed);
+        let (checks, worst) = readers
+            .into_iter()
+            .map(|reader| reader.join().expect("reader thread"))
+            .fold((0, Duration::ZERO), |(total, worst), (checks, w)| {
+                (total + checks, worst.max(w))
+            });
+        (checks, worst, writer.join().expect("writer thread"))
+    });
+    println!(
+        "{name:>8}: {:>12.0} checks/s, slowest check {worst:>10.2?}, {writes} writes",
+        checks as f64 / RUN_FOR.as_secs_f64()
+    );
+}
+
+fn main() {
+    let ctx = PermissionContext {
+        dimension: Some("minecraft:overworld"),
+        gamemode: Some("survival"),
+    };
+    let store = RwLock::new(build_store());
+
+    // Checks that take the store's read lock, as the handler used to.
+    run("locked", &store, |uuid, node| {
+        store
+            .blocking_read()
+            .snapshot()
+            .check_permission(uuid, node, &ctx)
+    });
+
+    // Checks against the latest snapshot, as the handler does now.
+    let snapshot = store.blocking_read().snapshot_handle();
+    run("snapshot", &store, |uuid, node| {
+        snapshot.load().check_permission(uuid, node, &ctx)
+    });
+}
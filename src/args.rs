@@ -0,0 +1,299 @@
+//! Command arguments beyond the ones Pumpkin provides.
+
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use pumpkin::command::CommandSender;
+use pumpkin::command::args::players::PlayersArgumentConsumer;
+use pumpkin::command::args::simple::SimpleArgConsumer;
+use pumpkin::command::args::{
+    Arg, ArgumentConsumer, ArgumentType, CommandSuggestion, ConsumeResult, ConsumedArgs,
+    GetClientSideArgParser, RawArgs, SuggestResult, SuggestionProviders,
+};
+use pumpkin::command::dispatcher::CommandError;
+use pumpkin::server::Server;
+use pumpkin_util::text::TextComponent;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::cache::Snapshot;
+use crate::store::{PermissionStore, display_name};
+
+/// Most suggestions sent for one argument.
+const MAX_SUGGESTIONS: usize = 50;
+
+/// A player a command applies to, online or not.
+pub struct Target {
+    pub uuid: Uuid,
+    /// How replies refer to the player, see `store::display_name`.
+    pub name: String,
+    /// The username to store, empty for a UUID nobody has joined with yet.
+    pub username: String,
+}
+
+/// Accepts everything `PlayersArgumentConsumer` does (online names and
+/// selectors such as `@a`), plus a UUID or the username of any player Seed
+/// has stored data for. Resolve it with `find_targets`.
+pub struct TargetArgumentConsumer;
+
+impl GetClientSideArgParser for TargetArgumentConsumer {
+    fn get_client_side_parser(&self) -> ArgumentType<'_> {
+        // The client's entity parser also accepts offline names and UUIDs.
+        PlayersArgumentConsumer.get_client_side_parser()
+    }
+
+    fn get_client_side_suggestion_type_override(&self) -> Option<SuggestionProviders> {
+        PlayersArgumentConsumer.get_client_side_suggestion_type_override()
+    }
+}
+
+impl ArgumentConsumer for TargetArgumentConsumer {
+    fn consume<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        server: &'a Server,
+        args: &mut RawArgs<'a>,
+    ) -> ConsumeResult<'a> {
+        let Some(raw) = args.pop() else {
+            return Box::pin(async { None });
+        };
+        let online = PlayersArgumentConsumer.consume(sender, server, &mut vec![raw]);
+        Box::pin(async move {
+            match online.await {
+                // A selector matching nobody is still a selector.
+                Some(Arg::Players(players)) if !players.is_empty() || raw.starts_with('@') => {
+                    Some(Arg::Players(players))
+                }
+                _ if raw.starts_with('@') => None,
+                // Looked up in the store by `find_targets`.
+                _ => Some(Arg::Simple(raw)),
+            }
+        })
+    }
+}
+
+/// The players named by the `TargetArgumentConsumer` argument `name`.
+/// A UUID without stored data is accepted as is, so data can be set up for
+/// players who have never joined.
+pub fn find_targets(
+    args: &ConsumedArgs,
+    name: &str,
+    store: &PermissionStore,
+) -> Result<Vec<Target>, CommandError> {
+    match args.get(name) {
+        Some(Arg::Players(players)) => Ok(players
+            .iter()
+            .map(|player| Target {
+                uuid: player.gameprofile.id,
+                name: player.gameprofile.name.clone(),
+                username: player.gameprofile.name.clone(),
+            })
+            .collect()),
+        Some(Arg::Simple(raw)) => offline_target(raw, store)
+            .map(|target| vec![target])
+            .map_err(|e| CommandError::CommandFailed(TextComponent::text(e))),
+        _ => Err(CommandError::InvalidConsumption(Some(name.to_string()))),
+    }
+}
+
+fn offline_target(raw: &str, store: &PermissionStore) -> Result<Target, String> {
+    if let Ok(uuid) = Uuid::parse_str(raw) {
+        // The join handler stores the name once the player shows up.
+        let username = store
+            .players
+            .get(&uuid)
+            .map(|pd| pd.username.clone())
+            .unwrap_or_default();
+        return Ok(Target {
+            uuid,
+            name: display_name(&uuid, &username),
+            username,
+        });
+    }
+    let mut matches = store
+        .players
+        .iter()
+        .filter(|(_, pd)| pd.username.eq_ignore_ascii_case(raw));
+    match (matches.next(), matches.next()) {
+        (Some((uuid, pd)), None) => Ok(Target {
+            uuid: *uuid,
+            name: pd.username.clone(),
+            username: pd.username.clone(),
+        }),
+        (Some(_), Some(_)) => Err(format!(
+            "Several stored players were named '{raw}', use their UUID instead"
+        )),
+        (None, _) => Err(format!(
+            "Player '{raw}' is not online and has no stored data, use their UUID instead"
+        )),
+    }
+}
+
+/// A group name, suggesting existing groups. Parsed like `SimpleArgConsumer`,
+/// so read it with `SimpleArgConsumer::find_arg`.
+pub struct GroupArgumentConsumer(pub Arc<RwLock<PermissionStore>>);
+
+impl GetClientSideArgParser for GroupArgumentConsumer {
+    fn get_client_side_parser(&self) -> ArgumentType<'_> {
+        SimpleArgConsumer.get_client_side_parser()
+    }
+
+    fn get_client_side_suggestion_type_override(&self) -> Option<SuggestionProviders> {
+        Some(SuggestionProviders::AskServer)
+    }
+}
+
+impl ArgumentConsumer for GroupArgumentConsumer {
+    fn consume<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        server: &'a Server,
+        args: &mut RawArgs<'a>,
+    ) -> ConsumeResult<'a> {
+        SimpleArgConsumer.consume(sender, server, args)
+    }
+
+    fn suggest<'a>(
+        &'a self,
+        _sender: &'a CommandSender,
+        _server: &'a Server,
+        input: &'a str,
+    ) -> SuggestResult<'a> {
+        Box::pin(async move {
+            let store = self.0.read().await;
+            let names: BTreeSet<&str> = store
+                .groups
+                .keys()
+                .map(String::as_str)
+                .filter(|name| name.starts_with(input))
+                .collect();
+            Ok(Some(suggestions(names)))
+        })
+    }
+}
+
+/// Nodes `SeenNodes` records at most, so plugins checking generated nodes
+/// can't grow it without bound.
+const MAX_SEEN: usize = 4096;
+
+/// Permission nodes seen in permission checks, offered as completions
+/// alongside the nodes in stored data.
+#[derive(Default)]
+pub struct SeenNodes {
+    nodes: ArcSwap<HashSet<String>>,
+}
+
+impl SeenNodes {
+    /// Records a checked node. Nearly every check is for a node seen before
+    /// or comes after the set is full, neither of which takes a lock; a new
+    /// node copies the set, which happens at most `MAX_SEEN` times.
+    pub fn observe(&self, node: &str) {
+        let nodes = self.nodes.load();
+        if nodes.len() >= MAX_SEEN || nodes.contains(node) {
+            return;
+        }
+        self.nodes.rcu(|nodes| {
+            let mut nodes = HashSet::clone(nodes);
+            if nodes.len() < MAX_SEEN {
+                nodes.insert(node.to_string());
+            }
+            nodes
+        });
+    }
+}
+
+/// A permission node, suggesting nodes from stored data (as indexed in the
+/// snapshot) and from checks seen so far. Suggestions complete one dotted segment at a time, so typing
+/// `minecraft:command.` offers `minecraft:command.gamemode.` rather than every
+/// node below it, plus the `minecraft:command.*` wildcard. Parsed like
+/// `SimpleArgConsumer`, so read it with `SimpleArgConsumer::find_arg`.
+pub struct PermissionArgumentConsumer(pub Arc<ArcSwap<Snapshot>>, pub Arc<SeenNodes>);
+
+impl GetClientSideArgParser for PermissionArgumentConsumer {
+    fn get_client_side_parser(&self) -> ArgumentType<'_> {
+        SimpleArgConsumer.get_client_side_parser()
+    }
+
+    fn get_client_side_suggestion_type_override(&self) -> Option<SuggestionProviders> {
+        Some(SuggestionProviders::AskServer)
+    }
+}
+
+impl ArgumentConsumer for PermissionArgumentConsumer {
+    fn consume<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        server: &'a Server,
+        args: &mut RawArgs<'a>,
+    ) -> ConsumeResult<'a> {
+        SimpleArgConsumer.consume(sender, server, args)
+    }
+
+    fn suggest<'a>(
+        &'a self,
+        _sender: &'a CommandSender,
+        _server: &'a Server,
+        input: &'a str,
+    ) -> SuggestResult<'a> {
+        Box::pin(async move {
+            // Group entries may be negated; complete the node after the `-`.
+            let (negation, partial) = match input.strip_prefix('-') {
+                Some(rest) => ("-", rest),
+                None => ("", input),
+            };
+            let seen = self.1.nodes.load();
+            let snapshot = self.0.load();
+            let known = seen
+                .iter()
+                .map(String::as_str)
+                .filter(|node| node.starts_with(partial))
+                .chain(snapshot.nodes_with_prefix(partial));
+
+            let mut completions = BTreeSet::new();
+            for node in known {
+                let Some(rest) = node.strip_prefix(partial) else {
+                    continue;
+                };
+                match rest.find('.') {
+                    Some(dot) => {
+                        completions.insert(format!("{negation}{partial}{}", &rest[..=dot]));
+                    }
+                    None => {
+                        completions.insert(format!("{negation}{node}"));
+                    }
+                }
+            }
+            if partial.ends_with('.') && !completions.is_empty() {
+                completions.insert(format!("{negation}{partial}*"));
+            }
+            Ok(Some(suggestions(completions.iter().map(String::as_str))))
+        })
+    }
+}
+
+fn suggestions<'a>(values: impl IntoIterator<Item = &'a str>) -> Vec<CommandSuggestion> {
+    values
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|value| CommandSuggestion::new(value.to_string(), None))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seen_nodes_stop_recording_when_full() {
+        let seen = SeenNodes::default();
+        for i in 0..MAX_SEEN + 10 {
+            seen.observe(&format!("plugin:node.{i}"));
+        }
+        seen.observe("plugin:node.0");
+        let nodes = seen.nodes.load();
+        assert_eq!(nodes.len(), MAX_SEEN);
+        assert!(nodes.contains("plugin:node.0"));
+        assert!(!nodes.contains(&format!("plugin:node.{MAX_SEEN}")));
+    }
+}
@@ -0,0 +1,233 @@
+//! Crash-safe replacement of several files in one folder at once.
+//!
+//! New contents are written to `<name>.tmp` and synced, then a `save.commit`
+//! marker listing the files is put in place before any of them is renamed.
+//! The marker is itself written aside and renamed, so it is either complete
+//! or absent. A crash before the marker exists leaves the old files
+//! untouched; a crash after it is finished by `recover` before the next load,
+//! so either every file is replaced or none is.
+
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+const MARKER: &str = "save.commit";
+
+/// Replaces each `(file name, contents)` in `folder`, all or nothing.
+pub fn write_files(folder: &Path, files: &[(&str, String)]) -> Result<(), String> {
+    for (name, content) in files {
+        write_synced(&folder.join(format!("{name}.tmp")), content.as_bytes())
+            .map_err(|e| format!("Failed to write {name}: {e}"))?;
+    }
+    // The temp files must be on disk before a marker can point at them.
+    sync_folder(folder).map_err(|e| format!("Failed to sync data folder: {e}"))?;
+
+    let names: Vec<&str> = files.iter().map(|(name, _)| *name).collect();
+    let marker = folder.join(format!("{MARKER}.tmp"));
+    write_synced(&marker, names.join("\n").as_bytes())
+        .map_err(|e| format!("Failed to write {MARKER}: {e}"))?;
+    fs::rename(&marker, folder.join(MARKER))
+        .map_err(|e| format!("Failed to write {MARKER}: {e}"))?;
+    sync_folder(folder).map_err(|e| format!("Failed to sync data folder: {e}"))?;
+
+    // From here on the save is committed: if a rename fails, the next
+    // `recover` completes it.
+    finish(folder, &names)
+}
+
+/// Whether a save was committed but not finished, i.e. `recover` would
+/// replace files rather than just clean up.
+pub fn is_interrupted(folder: &Path) -> bool {
+    folder.join(MARKER).exists()
+}
+
+/// Completes or discards a save interrupted by a crash. Call before reading
+/// the files `names` written by `write_files`. Refuses, leaving everything as
+/// it is, if the marker doesn't describe a save that can be completed.
+pub fn recover(folder: &Path, names: &[&str]) -> Result<(), String> {
+    // A marker that was never renamed into place belongs to a save that
+    // never committed.
+    remove_if_present(folder, &format!("{MARKER}.tmp"))?;
+    match fs::read_to_string(folder.join(MARKER)) {
+        Ok(marker) => {
+            let committed: Vec<&str> = marker.lines().collect();
+            check_committed(folder, names, &committed)?;
+            finish(folder, &committed)?;
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to read {MARKER}: {e}")),
+    }
+    // Without a marker, leftover temp files are from a save that never
+    // committed.
+    for name in names {
+        remove_if_present(folder, &format!("{name}.tmp"))?;
+    }
+    Ok(())
+}
+
+/// Checks that the marker lists only `names`, and that `finish` can complete
+/// it. Temp files are on disk before the marker, and `finish` renames them in
+/// the marker's order, so only a leading run of them can be gone already.
+fn check_committed(folder: &Path, names: &[&str], committed: &[&str]) -> Result<(), String> {
+    let mut pending = false;
+    for name in committed {
+        if !names.contains(name) {
+            return Err(format!(
+                "{MARKER} lists unknown file '{name}', check the data folder and remove it"
+            ));
+        }
+        let has_temp = folder.join(format!("{name}.tmp")).exists();
+        if pending && !has_temp {
+            return Err(format!(
+                "{MARKER} lists {name} but {name}.tmp is missing, check the data folder and remove {MARKER}"
+            ));
+        }
+        pending |= has_temp;
+    }
+    Ok(())
+}
+
+/// Renames each committed temp file into place, then removes the marker.
+/// Files already renamed by an earlier attempt are skipped.
+fn finish(folder: &Path, names: &[&str]) -> Result<(), String> {
+    for name in names {
+        let temp = folder.join(format!("{name}.tmp"));
+        if temp.exists() {
+            fs::rename(&temp, folder.join(name))
+                .map_err(|e| format!("Failed to replace {name}: {e}"))?;
+        }
+    }
+    sync_folder(folder).map_err(|e| format!("Failed to sync data folder: {e}"))?;
+    fs::remove_file(folder.join(MARKER)).map_err(|e| format!("Failed to remove {MARKER}: {e}"))?;
+    sync_folder(folder).map_err(|e| format!("Failed to sync data folder: {e}"))
+}
+
+fn remove_if_present(folder: &Path, name: &str) -> Result<(), String> {
+    match fs::remove_file(folder.join(name)) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove {name}: {e}")),
+    }
+}
+
+fn write_synced(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+/// Makes created, renamed and removed entries in `folder` durable. Windows
+/// can't open directories for syncing, and its renames are journaled anyway.
+#[cfg(unix)]
+fn sync_folder(folder: &Path) -> std::io::Result<()> {
+    File::open(folder)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_folder(_folder: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    const NAMES: [&str; 2] = ["groups.toml", "players.toml"];
+
+    /// A fresh folder holding the old contents of `NAMES`.
+    fn folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("seed-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        for name in NAMES {
+            fs::write(folder.join(name), "old").unwrap();
+        }
+        folder
+    }
+
+    fn read(folder: &Path, name: &str) -> Option<String> {
+        fs::read_to_string(folder.join(name)).ok()
+    }
+
+    fn leftovers(folder: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(folder)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| !NAMES.contains(&name.as_str()))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn write_files_replaces_every_file() {
+        let folder = folder("write-files");
+        let files = [("groups.toml", "new groups".to_string())];
+        write_files(&folder, &files).unwrap();
+        assert_eq!(read(&folder, "groups.toml").as_deref(), Some("new groups"));
+        assert_eq!(read(&folder, "players.toml").as_deref(), Some("old"));
+        assert!(leftovers(&folder).is_empty());
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn recover_rolls_back_without_a_marker() {
+        let folder = folder("roll-back");
+        fs::write(folder.join("groups.toml.tmp"), "new").unwrap();
+        // A crash while the marker was being written.
+        fs::write(folder.join("save.commit.tmp"), "groups.toml\nplay").unwrap();
+        recover(&folder, &NAMES).unwrap();
+        assert_eq!(read(&folder, "groups.toml").as_deref(), Some("old"));
+        assert!(leftovers(&folder).is_empty());
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn recover_rolls_forward_with_a_marker() {
+        let folder = folder("roll-forward");
+        for name in NAMES {
+            fs::write(folder.join(format!("{name}.tmp")), "new").unwrap();
+        }
+        fs::write(folder.join(MARKER), NAMES.join("\n")).unwrap();
+        recover(&folder, &NAMES).unwrap();
+        for name in NAMES {
+            assert_eq!(read(&folder, name).as_deref(), Some("new"));
+        }
+        assert!(leftovers(&folder).is_empty());
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn recover_completes_an_interrupted_finish() {
+        let folder = folder("partial-finish");
+        // `groups.toml` was renamed into place before the crash.
+        fs::write(folder.join("groups.toml"), "new").unwrap();
+        fs::write(folder.join("players.toml.tmp"), "new").unwrap();
+        fs::write(folder.join(MARKER), NAMES.join("\n")).unwrap();
+        recover(&folder, &NAMES).unwrap();
+        assert_eq!(read(&folder, "players.toml").as_deref(), Some("new"));
+        assert!(leftovers(&folder).is_empty());
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn recover_refuses_markers_it_cannot_complete() {
+        let folder = folder("bad-marker");
+        fs::write(folder.join("groups.toml.tmp"), "new").unwrap();
+
+        // A temp file listed after a pending one is missing.
+        fs::write(folder.join(MARKER), NAMES.join("\n")).unwrap();
+        assert!(recover(&folder, &NAMES).is_err());
+
+        // A name that isn't one of the files.
+        fs::write(folder.join(MARKER), "groups.toml\nplay").unwrap();
+        assert!(recover(&folder, &NAMES).is_err());
+
+        // Nothing was touched.
+        assert_eq!(read(&folder, "groups.toml").as_deref(), Some("old"));
+        assert_eq!(read(&folder, "groups.toml.tmp").as_deref(), Some("new"));
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
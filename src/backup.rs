@@ -0,0 +1,165 @@
+//! Timestamped copies of the permission data under `backups/`, kept in the
+//! TOML layout whatever the storage backend, so they can be read and edited
+//! by hand.
+
+use std::cmp::Reverse;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::duration;
+use crate::storage::{Changes, Storage, StoredData, TomlStorage};
+use crate::store::PermissionStore;
+
+pub struct Backups {
+    folder: PathBuf,
+    /// How many backups to keep; older ones are deleted on `create`.
+    keep: usize,
+}
+
+impl Backups {
+    pub fn new(data_folder: &Path, keep: usize) -> Self {
+        Self {
+            folder: data_folder.join("backups"),
+            keep,
+        }
+    }
+
+    /// Writes the store's current contents to a new backup and deletes the
+    /// oldest ones beyond `keep`. Returns the new backup's id.
+    pub fn create(&self, store: &PermissionStore) -> Result<String, String> {
+        let now = duration::now();
+        let mut id = now.to_string();
+        let mut n = 1;
+        while self.folder.join(&id).exists() {
+            n += 1;
+            id = format!("{now}-{n}");
+        }
+        let path = self.folder.join(&id);
+        fs::create_dir_all(&path).map_err(|e| format!("Failed to create backup {id}: {e}"))?;
+        TomlStorage::new(path).save(store, &Changes::everything(store))?;
+        self.prune()?;
+        Ok(id)
+    }
+
+    /// Backup ids with their creation time, newest first.
+    pub fn list(&self) -> Result<Vec<(String, u64)>, String> {
+        let entries = match fs::read_dir(&self.folder) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read backups folder: {e}")),
+        };
+        let mut backups = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read backups folder: {e}"))?;
+            let id = entry.file_name().to_string_lossy().into_owned();
+            // Anything not named like a backup was put there by someone else.
+            if let Some(key) = sort_key(&id) {
+                backups.push((key, id));
+            }
+        }
+        backups.sort_by_key(|(key, _)| Reverse(*key));
+        Ok(backups
+            .into_iter()
+            .map(|((created, _), id)| (id, created))
+            .collect())
+    }
+
+    /// Reads backup `id`.
+    pub fn read(&self, id: &str) -> Result<StoredData, String> {
+        let path = self.folder.join(id);
+        if sort_key(id).is_none() || !path.is_dir() {
+            return Err(format!("Backup '{id}' does not exist"));
+        }
+        let data = TomlStorage::new(path).load()?;
+        if data.groups.is_none() {
+            return Err(format!("Backup '{id}' has no groups.toml"));
+        }
+        Ok(data)
+    }
+
+    fn prune(&self) -> Result<(), String> {
+        // Never delete the backup that was just made.
+        for (id, _) in self.list()?.into_iter().skip(self.keep.max(1)) {
+            fs::remove_dir_all(self.folder.join(&id))
+                .map_err(|e| format!("Failed to delete old backup {id}: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Orders ids of the form `<unix time>` or `<unix time>-<n>`, the latter for
+/// several backups made within the same second.
+fn sort_key(id: &str) -> Option<(u64, u32)> {
+    let (created, n) = match id.split_once('-') {
+        Some((created, n)) => (created, n.parse().ok()?),
+        None => (id, 1),
+    };
+    Some((created.parse().ok()?, n))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn temp_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("seed-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[test]
+    fn ids_sort_by_time_then_sequence() {
+        let mut ids = ["100-10", "99", "100-2", "100", "abc", "100-x", "-1"];
+        ids.sort_by_key(|id| sort_key(id));
+        assert_eq!(ids, ["abc", "100-x", "-1", "99", "100", "100-2", "100-10"]);
+    }
+
+    #[test]
+    fn list_is_newest_first_and_skips_other_folders() {
+        let folder = temp_folder("backup-list");
+        let backups = Backups::new(&folder, 10);
+        for id in ["100", "100-2", "99", "notes"] {
+            fs::create_dir_all(backups.folder.join(id)).unwrap();
+        }
+        let ids: Vec<String> = backups
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, ["100-2", "100", "99"]);
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn create_keeps_only_the_newest() {
+        let folder = temp_folder("backup-prune");
+        let storage = Arc::new(TomlStorage::new(folder.clone()));
+        let groups = HashMap::from([("default".to_string(), Default::default())]);
+        let store = PermissionStore::new(
+            folder.clone(),
+            storage,
+            groups,
+            HashMap::new(),
+            HashMap::new(),
+        );
+        let backups = Backups::new(&folder, 2);
+        fs::create_dir_all(backups.folder.join("1")).unwrap();
+        let ids: Vec<String> = (0..3).map(|_| backups.create(&store).unwrap()).collect();
+
+        let kept: Vec<String> = backups
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(kept, [ids[2].clone(), ids[1].clone()]);
+        assert!(backups.read(&ids[2]).unwrap().groups.is_some());
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
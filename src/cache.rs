@@ -0,0 +1,508 @@
+//! Precomputed indexes that keep permission checks allocation-free.
+//!
+//! Each group and player has an `EntryIndex` of its entries, keyed by exact
+//! node or by wildcard prefix, so a check only probes the prefixes of the node
+//! being checked instead of scanning every entry. Groups also cache their
+//! inheritance chain and players their weight-ordered group list.
+//!
+//! Checks read these through an immutable `Snapshot`. The store publishes a
+//! new snapshot after every mutation, sharing the indexes that didn't change,
+//! so a check never waits for a writer.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::{Bound, Range};
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::duration;
+use crate::node::{self, PermissionContext};
+
+/// A stored entry, pre-split for matching.
+pub struct IndexedEntry {
+    /// The entry as stored, e.g. `-minecraft:command.*[gamemode=creative]`.
+    pub raw: String,
+    pub granted: bool,
+    /// Where the `key=value,...` qualifiers are within `raw`.
+    qualifiers: Range<usize>,
+    qualifier_count: usize,
+    /// Unix time the entry stops applying at, for temporary entries.
+    pub expires: Option<u64>,
+}
+
+impl IndexedEntry {
+    fn qualifiers(&self) -> &str {
+        &self.raw[self.qualifiers.clone()]
+    }
+}
+
+#[derive(Default)]
+pub struct EntryIndex {
+    exact: HashMap<String, Vec<IndexedEntry>>,
+    /// Keyed by the wildcard's prefix: `minecraft:command.` for
+    /// `minecraft:command.*`, and the empty string for `*`.
+    wildcard: HashMap<String, Vec<IndexedEntry>>,
+}
+
+impl EntryIndex {
+    /// Adds a group entry, which is negated if it starts with `-`.
+    pub fn insert(&mut self, raw: &str) {
+        let (entry, granted) = node::parse(raw);
+        self.add(raw, entry, granted, None);
+    }
+
+    /// Adds a player entry, which is taken as written: whether it grants is
+    /// decided by the list it came from.
+    pub fn insert_literal(&mut self, raw: &str, granted: bool, expires: Option<u64>) {
+        self.add(raw, raw, granted, expires);
+    }
+
+    /// `entry` is `raw` without any negation prefix.
+    fn add(&mut self, raw: &str, entry: &str, granted: bool, expires: Option<u64>) {
+        let (pattern, qualifiers) = node::split_qualifiers(entry);
+        // Qualifiers sit just before the closing `]`, if there are any.
+        let end = raw.len() - usize::from(!qualifiers.is_empty());
+        let indexed = IndexedEntry {
+            raw: raw.to_string(),
+            granted,
+            qualifiers: end - qualifiers.len()..end,
+            qualifier_count: node::qualifier_pairs(qualifiers).count(),
+            expires,
+        };
+        let (map, key) = match node::wildcard_prefix(pattern) {
+            Some(prefix) => (&mut self.wildcard, prefix),
+            None => (&mut self.exact, pattern),
+        };
+        map.entry(key.to_string()).or_default().push(indexed);
+    }
+
+    /// The nodes of the entries, without negation or qualifiers. Wildcards
+    /// keep their `*`.
+    pub fn nodes(&self) -> impl Iterator<Item = String> {
+        let exact = self.exact.keys().cloned();
+        exact.chain(self.wildcard.keys().map(|prefix| format!("{prefix}*")))
+    }
+
+    /// The best entry matching `node` in `ctx` at `now`. Entries rank by
+    /// specificity first: an exact match beats any wildcard, and a longer
+    /// wildcard prefix beats a shorter one. Then an entry with more context
+    /// qualifiers wins, so `node[dimension=...]` overrides a bare `node`
+    /// wherever it applies, and finally a negation beats a grant.
+    pub fn best(&self, node: &str, ctx: &PermissionContext, now: u64) -> Option<&IndexedEntry> {
+        let boundaries = node
+            .char_indices()
+            .filter(|&(i, c)| (c == ':' || c == '.') && i + 1 < node.len())
+            .map(|(i, _)| &node[..=i]);
+        let wildcards = std::iter::once("")
+            .chain(boundaries)
+            .filter_map(|prefix| Some((prefix.len(), self.wildcard.get(prefix)?)));
+        self.exact
+            .get(node)
+            .map(|entries| (usize::MAX, entries))
+            .into_iter()
+            .chain(wildcards)
+            .flat_map(|(specificity, entries)| entries.iter().map(move |e| (specificity, e)))
+            .filter(|(_, e)| e.expires.is_none_or(|expires| expires > now))
+            .filter(|(_, e)| node::qualifiers_apply(e.qualifiers(), ctx))
+            .max_by_key(|(specificity, e)| (*specificity, e.qualifier_count, !e.granted))
+            .map(|(_, e)| e)
+    }
+}
+
+#[derive(Default)]
+pub struct PlayerIndex {
+    /// Extra permissions and denials, permanent and temporary.
+    pub entries: EntryIndex,
+    /// Group memberships by descending weight, with their expiry if temporary.
+    pub groups: Vec<(String, Option<u64>)>,
+    /// The player's own meta.
+    pub meta: HashMap<String, String>,
+}
+
+impl PlayerIndex {
+    /// The player's groups in effect at `now`, heaviest first.
+    pub fn active_groups(&self, now: u64) -> impl Iterator<Item = &str> {
+        self.groups
+            .iter()
+            .filter(move |(_, expires)| expires.is_none_or(|expires| expires > now))
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// The rule that decided a permission check, see `Snapshot::trace_permission`.
+#[derive(Debug, Clone, Copy)]
+pub enum Decision<'a> {
+    /// One of the player's own entries; `list` names the field it is in.
+    Player {
+        list: &'static str,
+        entry: &'a str,
+        granted: bool,
+    },
+    /// An entry of `source`, reached through the player's group `group`.
+    /// `source` differs from `group` when the entry was inherited.
+    Group {
+        group: &'a str,
+        source: &'a str,
+        entry: &'a str,
+    },
+}
+
+impl Decision<'_> {
+    pub fn granted(&self) -> bool {
+        match self {
+            Decision::Player { granted, .. } => *granted,
+            Decision::Group { entry, .. } => node::parse(entry).1,
+        }
+    }
+}
+
+/// Everything a permission check reads, as of one point in time.
+#[derive(Clone, Default)]
+pub struct Snapshot {
+    pub group_entries: HashMap<String, Arc<EntryIndex>>,
+    /// Each group followed by its ancestors, nearest first.
+    pub group_chains: HashMap<String, Vec<String>>,
+    /// Each group's own meta.
+    pub group_meta: HashMap<String, Arc<HashMap<String, String>>>,
+    pub players: HashMap<Uuid, Arc<PlayerIndex>>,
+    /// Every node in a group's or player's entries, with how many of them
+    /// use it, sorted so completions can look nodes up by prefix.
+    pub nodes: Arc<BTreeMap<String, usize>>,
+}
+
+impl Snapshot {
+    /// Counts the nodes of an `EntryIndex` being added to the snapshot, or
+    /// uncounts them if it's being replaced or removed.
+    pub fn count_nodes(&mut self, entries: &EntryIndex, added: bool) {
+        let nodes = Arc::make_mut(&mut self.nodes);
+        for node in entries.nodes() {
+            if added {
+                *nodes.entry(node).or_default() += 1;
+            } else if let Some(count) = nodes.get_mut(&node) {
+                *count -= 1;
+                if *count == 0 {
+                    nodes.remove(&node);
+                }
+            }
+        }
+    }
+
+    /// Stored nodes starting with `prefix`, in order.
+    pub fn nodes_with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> {
+        self.nodes
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .map(|(node, _)| node.as_str())
+            .take_while(move |node| node.starts_with(prefix))
+    }
+
+    /// Resolve a permission for a player. Returns Some(true/false) if Seed has
+    /// an opinion, None to fall through to Pumpkin's default.
+    ///
+    /// Within each layer the most specific matching entry wins. Player
+    /// overrides are consulted before any group, and a denial beats an extra
+    /// permission of equal specificity. The player's groups are then tried
+    /// from heaviest to lightest, and the first one with an opinion decides.
+    ///
+    /// Expired temporary entries are ignored even before they are purged, and
+    /// entries with context qualifiers only apply when `ctx` satisfies them.
+    pub fn check_permission(
+        &self,
+        uuid: &Uuid,
+        node: &str,
+        ctx: &PermissionContext,
+    ) -> Option<bool> {
+        self.trace_permission(uuid, node, ctx)
+            .map(|decision| decision.granted())
+    }
+
+    /// Resolves a meta value for a player like `PermissionStore::get_meta`:
+    /// the player's own meta wins, then their groups from heaviest to
+    /// lightest, each walked nearest first.
+    pub fn get_meta(&self, uuid: &Uuid, key: &str) -> Option<&str> {
+        let now = duration::now();
+        let index = self.players.get(uuid);
+        if let Some(value) = index.and_then(|index| index.meta.get(key)) {
+            return Some(value);
+        }
+        let mut groups = index
+            .into_iter()
+            .flat_map(|index| index.active_groups(now))
+            .peekable();
+        let fallback = groups.peek().is_none().then_some("default");
+        groups
+            .chain(fallback)
+            .flat_map(|group| self.group_chains.get(group).into_iter().flatten())
+            .find_map(|name| self.group_meta.get(name)?.get(key))
+            .map(String::as_str)
+    }
+
+    /// Like `check_permission`, but reports the rule that decided the check.
+    pub fn trace_permission(
+        &self,
+        uuid: &Uuid,
+        node: &str,
+        ctx: &PermissionContext,
+    ) -> Option<Decision<'_>> {
+        let now = duration::now();
+        let index = self.players.get(uuid);
+        if let Some(entry) = index.and_then(|index| index.entries.best(node, ctx, now)) {
+            let list = match (entry.granted, entry.expires.is_some()) {
+                (true, false) => "extra_permissions",
+                (true, true) => "temp_permissions",
+                (false, false) => "denied_permissions",
+                (false, true) => "temp_denied_permissions",
+            };
+            return Some(Decision::Player {
+                list,
+                entry: &entry.raw,
+                granted: entry.granted,
+            });
+        }
+
+        // Players without data or without any group in effect are treated as
+        // members of `default`.
+        let mut groups = index
+            .into_iter()
+            .flat_map(|index| index.active_groups(now))
+            .peekable();
+        let fallback = groups.peek().is_none().then_some("default");
+        groups.chain(fallback).find_map(|group| {
+            let (source, entry) = self.trace_group(group, node, ctx)?;
+            Some(Decision::Group {
+                group,
+                source,
+                entry,
+            })
+        })
+    }
+
+    /// Resolve a permission against a group and its ancestors, returning the
+    /// deciding group and entry. The first group in the chain with a matching
+    /// entry decides, so negated (`-node`) entries override anything inherited
+    /// from parents.
+    pub fn trace_group(
+        &self,
+        group_name: &str,
+        node: &str,
+        ctx: &PermissionContext,
+    ) -> Option<(&str, &str)> {
+        self.group_chains.get(group_name)?.iter().find_map(|name| {
+            // Group entries never expire, so any time will do.
+            let entry = self.group_entries.get(name)?.best(node, ctx, 0)?;
+            Some((name.as_str(), entry.raw.as_str()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SURVIVAL: PermissionContext = PermissionContext {
+        dimension: Some("minecraft:overworld"),
+        gamemode: Some("survival"),
+    };
+    const CREATIVE: PermissionContext = PermissionContext {
+        dimension: Some("minecraft:overworld"),
+        gamemode: Some("creative"),
+    };
+
+    fn index(entries: &[&str]) -> EntryIndex {
+        let mut index = EntryIndex::default();
+        for entry in entries {
+            index.insert(entry);
+        }
+        index
+    }
+
+    fn best<'a>(index: &'a EntryIndex, node: &str, ctx: &PermissionContext) -> Option<&'a str> {
+        index.best(node, ctx, 0).map(|entry| entry.raw.as_str())
+    }
+
+    #[test]
+    fn wildcards_match_whole_segments() {
+        let cases: [(&[&str], &str, Option<&str>); 10] = [
+            (&["*"], "minecraft:command.kick", Some("*")),
+            (&["*"], "seed", Some("*")),
+            (
+                &["minecraft:*"],
+                "minecraft:command.kick",
+                Some("minecraft:*"),
+            ),
+            (&["minecraft:*"], "other:command.kick", None),
+            (
+                &["minecraft:command.*"],
+                "minecraft:command.kick",
+                Some("minecraft:command.*"),
+            ),
+            (&["minecraft:command.*"], "minecraft:command", None),
+            (&["minecraft:command.*"], "minecraft:commands", None),
+            (&["minecraft:command.*"], "minecraft:commands.kick", None),
+            (&["minecraft:command"], "minecraft:command.kick", None),
+            (&["minecraft:comm*"], "minecraft:command", None),
+        ];
+        for (entries, node, expected) in cases {
+            assert_eq!(
+                best(&index(entries), node, &SURVIVAL),
+                expected,
+                "{entries:?} {node}"
+            );
+        }
+    }
+
+    #[test]
+    fn most_specific_entry_wins() {
+        let index = index(&[
+            "*",
+            "-minecraft:*",
+            "minecraft:command.*",
+            "-minecraft:command.kick",
+        ]);
+        let cases = [
+            ("minecraft:command.kick", "-minecraft:command.kick"),
+            ("minecraft:command.ban", "minecraft:command.*"),
+            ("minecraft:other", "-minecraft:*"),
+            ("seed:admin", "*"),
+        ];
+        for (node, expected) in cases {
+            assert_eq!(best(&index, node, &SURVIVAL), Some(expected), "{node}");
+        }
+    }
+
+    #[test]
+    fn qualifiers_then_negation_break_ties() {
+        let index = index(&[
+            "minecraft:command.fly",
+            "-minecraft:command.fly",
+            "minecraft:command.fly[gamemode=creative]",
+            "minecraft:command.*[gamemode=creative,dimension=overworld]",
+        ]);
+        assert_eq!(
+            best(&index, "minecraft:command.fly", &SURVIVAL),
+            Some("-minecraft:command.fly")
+        );
+        // An exact node beats a wildcard with more qualifiers.
+        assert_eq!(
+            best(&index, "minecraft:command.fly", &CREATIVE),
+            Some("minecraft:command.fly[gamemode=creative]")
+        );
+        // Nodes without an exact entry fall through to the wildcard.
+        assert_eq!(
+            best(&index, "minecraft:command.kick", &CREATIVE),
+            Some("minecraft:command.*[gamemode=creative,dimension=overworld]")
+        );
+    }
+
+    #[test]
+    fn expired_entries_are_ignored() {
+        let mut index = EntryIndex::default();
+        index.insert_literal("minecraft:command.fly", true, Some(100));
+        assert!(index.best("minecraft:command.fly", &SURVIVAL, 99).is_some());
+        assert!(
+            index
+                .best("minecraft:command.fly", &SURVIVAL, 100)
+                .is_none()
+        );
+    }
+
+    /// `member` grants everything below `minecraft:command`, `mod` inherits
+    /// from it but denies `kick`, and `admin` is heavier than both.
+    fn with_groups(player: PlayerIndex) -> (Snapshot, Uuid) {
+        let uuid = Uuid::from_u128(1);
+        let mut snapshot = Snapshot::default();
+        for (name, entries, chain) in [
+            ("member", &["minecraft:command.*"][..], &["member"][..]),
+            ("mod", &["-minecraft:command.kick"], &["mod", "member"]),
+            ("admin", &["minecraft:command.kick"], &["admin"]),
+        ] {
+            snapshot
+                .group_entries
+                .insert(name.to_string(), Arc::new(index(entries)));
+            let chain = chain.iter().map(|g| g.to_string()).collect();
+            snapshot.group_chains.insert(name.to_string(), chain);
+        }
+        snapshot.players.insert(uuid, Arc::new(player));
+        (snapshot, uuid)
+    }
+
+    fn player(groups: &[&str]) -> PlayerIndex {
+        PlayerIndex {
+            groups: groups.iter().map(|g| (g.to_string(), None)).collect(),
+            ..PlayerIndex::default()
+        }
+    }
+
+    #[test]
+    fn child_entries_override_inherited_ones() {
+        let (snapshot, uuid) = with_groups(player(&["mod"]));
+        let check = |node| snapshot.check_permission(&uuid, node, &SURVIVAL);
+        assert_eq!(check("minecraft:command.kick"), Some(false));
+        assert_eq!(check("minecraft:command.ban"), Some(true));
+        assert_eq!(check("seed:admin"), None);
+    }
+
+    #[test]
+    fn heaviest_group_then_player_overrides_decide() {
+        // Groups are stored heaviest first.
+        let (snapshot, uuid) = with_groups(player(&["admin", "mod"]));
+        assert_eq!(
+            snapshot.check_permission(&uuid, "minecraft:command.kick", &SURVIVAL),
+            Some(true)
+        );
+
+        let mut overridden = player(&["admin"]);
+        overridden
+            .entries
+            .insert_literal("minecraft:command.kick", false, None);
+        let (snapshot, uuid) = with_groups(overridden);
+        assert_eq!(
+            snapshot.check_permission(&uuid, "minecraft:command.kick", &SURVIVAL),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn meta_resolves_player_then_groups_then_ancestors() {
+        let meta = |pairs: &[(&str, &str)]| {
+            let pairs = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string()));
+            Arc::new(pairs.collect::<HashMap<_, _>>())
+        };
+        let mut player = player(&["admin", "mod"]);
+        player.meta.insert("suffix".to_string(), "!".to_string());
+        let (mut snapshot, uuid) = with_groups(player);
+        snapshot
+            .group_meta
+            .insert("admin".to_string(), meta(&[("prefix", "[A]")]));
+        snapshot
+            .group_meta
+            .insert("mod".to_string(), meta(&[("prefix", "[M]")]));
+        snapshot
+            .group_meta
+            .insert("member".to_string(), meta(&[("color", "gray")]));
+        assert_eq!(snapshot.get_meta(&uuid, "suffix"), Some("!"));
+        assert_eq!(snapshot.get_meta(&uuid, "prefix"), Some("[A]"));
+        assert_eq!(snapshot.get_meta(&uuid, "color"), Some("gray"));
+        assert_eq!(snapshot.get_meta(&uuid, "missing"), None);
+    }
+
+    #[test]
+    fn players_without_groups_fall_back_to_default() {
+        let (mut snapshot, uuid) = with_groups(player(&[]));
+        snapshot.group_entries.insert(
+            "default".to_string(),
+            Arc::new(index(&["minecraft:command.help"])),
+        );
+        snapshot
+            .group_chains
+            .insert("default".to_string(), vec!["default".to_string()]);
+        assert_eq!(
+            snapshot.check_permission(&uuid, "minecraft:command.help", &SURVIVAL),
+            Some(true)
+        );
+        let stranger = Uuid::from_u128(2);
+        assert_eq!(
+            snapshot.check_permission(&stranger, "minecraft:command.help", &SURVIVAL),
+            Some(true)
+        );
+    }
+}
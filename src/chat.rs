@@ -0,0 +1,338 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use pumpkin::plugin::api::events::player::player_chat::PlayerChatEvent;
+use pumpkin::plugin::{BoxFuture, Cancellable, Context, EventHandler};
+use pumpkin::server::Server;
+use pumpkin_util::text::TextComponent;
+use pumpkin_util::text::color::NamedColor;
+use uuid::Uuid;
+
+use crate::cache::Snapshot;
+use crate::store::{META_PREFIX, META_SUFFIX};
+
+/// Rewrites chat lines as `format` with the sender's prefix and suffix. The
+/// original event is cancelled and the formatted line sent to its recipients,
+/// so the handler logs the message itself in place of the server. Prefixes and
+/// suffixes come from the published snapshot, so chat never waits on a save.
+pub struct SeedChatHandler {
+    pub context: Arc<Context>,
+    pub snapshot: Arc<ArcSwap<Snapshot>>,
+    pub format: String,
+}
+
+impl EventHandler<PlayerChatEvent> for SeedChatHandler {
+    fn handle_blocking<'a>(
+        &'a self,
+        _server: &'a Arc<Server>,
+        event: &'a mut PlayerChatEvent,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            if event.cancelled() {
+                return;
+            }
+            let uuid = event.player.gameprofile.id;
+            let name = &event.player.gameprofile.name;
+            let line = {
+                let snapshot = self.snapshot.load();
+                chat_line(&self.format, &snapshot, &uuid, name, &event.message).build()
+            };
+            self.context.log(format!("<{name}> {}", event.message));
+            event.set_cancelled(true);
+            for recipient in &event.recipients {
+                recipient.send_system_message(&line).await;
+            }
+        })
+    }
+}
+
+/// Legacy formatting codes, as written after `&` or `§`.
+const CODES: &str = "0123456789abcdefklmnor";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Style {
+    color: Option<NamedColor>,
+    bold: bool,
+    italic: bool,
+    underlined: bool,
+    strikethrough: bool,
+    obfuscated: bool,
+}
+
+impl Style {
+    /// Applies one of `CODES`; anything else is ignored.
+    fn apply(&mut self, code: char) {
+        match code.to_ascii_lowercase() {
+            'k' => self.obfuscated = true,
+            'l' => self.bold = true,
+            'm' => self.strikethrough = true,
+            'n' => self.underlined = true,
+            'o' => self.italic = true,
+            'r' => *self = Style::default(),
+            code => {
+                if let Some(color) = color(code) {
+                    // Like vanilla, a color code also clears any formatting.
+                    *self = Style {
+                        color: Some(color),
+                        ..Style::default()
+                    };
+                }
+            }
+        }
+    }
+
+    fn component(self, text: String) -> TextComponent {
+        let mut component = TextComponent::text(text);
+        if let Some(color) = self.color {
+            component = component.color_named(color);
+        }
+        if self.bold {
+            component = component.bold();
+        }
+        if self.italic {
+            component = component.italic();
+        }
+        if self.underlined {
+            component = component.underlined();
+        }
+        if self.strikethrough {
+            component = component.strikethrough();
+        }
+        if self.obfuscated {
+            component = component.obfuscated();
+        }
+        component
+    }
+}
+
+fn color(code: char) -> Option<NamedColor> {
+    Some(match code {
+        '0' => NamedColor::Black,
+        '1' => NamedColor::DarkBlue,
+        '2' => NamedColor::DarkGreen,
+        '3' => NamedColor::DarkAqua,
+        '4' => NamedColor::DarkRed,
+        '5' => NamedColor::DarkPurple,
+        '6' => NamedColor::Gold,
+        '7' => NamedColor::Gray,
+        '8' => NamedColor::DarkGray,
+        '9' => NamedColor::Blue,
+        'a' => NamedColor::Green,
+        'b' => NamedColor::Aqua,
+        'c' => NamedColor::Red,
+        'd' => NamedColor::LightPurple,
+        'e' => NamedColor::Yellow,
+        'f' => NamedColor::White,
+        _ => return None,
+    })
+}
+
+/// Builds a styled line from `&`/`§` color-coded text, carrying the style
+/// across segments the way the vanilla client does.
+#[derive(Default)]
+struct LineBuilder {
+    /// Finished segments and their style.
+    parts: Vec<(Style, String)>,
+    style: Style,
+    pending: String,
+}
+
+impl LineBuilder {
+    /// Appends text, interpreting its color codes.
+    fn push_coded(&mut self, text: &str) {
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if matches!(c, '&' | '§')
+                && let Some(&code) = chars.peek()
+                && CODES.contains(code.to_ascii_lowercase())
+            {
+                self.flush();
+                self.style.apply(code);
+                chars.next();
+            } else {
+                self.pending.push(c);
+            }
+        }
+    }
+
+    /// Appends text literally in the current style.
+    fn push_plain(&mut self, text: &str) {
+        self.pending.push_str(text);
+    }
+
+    fn flush(&mut self) {
+        if !self.pending.is_empty() {
+            let text = std::mem::take(&mut self.pending);
+            self.parts.push((self.style, text));
+        }
+    }
+
+    fn build(mut self) -> TextComponent {
+        self.flush();
+        self.parts
+            .into_iter()
+            .map(|(style, text)| style.component(text))
+            .fold(TextComponent::text(""), TextComponent::add_child)
+    }
+}
+
+/// The line for `name`'s chat `message`, with the prefix and suffix resolved
+/// for `uuid` like any other meta.
+fn chat_line(
+    template: &str,
+    snapshot: &Snapshot,
+    uuid: &Uuid,
+    name: &str,
+    message: &str,
+) -> LineBuilder {
+    render(
+        template,
+        snapshot.get_meta(uuid, META_PREFIX).unwrap_or(""),
+        name,
+        snapshot.get_meta(uuid, META_SUFFIX).unwrap_or(""),
+        message,
+    )
+}
+
+/// Fills in a chat template. Color codes are honored in the template, prefix
+/// and suffix, while the player's name and message are inserted literally.
+fn render(template: &str, prefix: &str, name: &str, suffix: &str, message: &str) -> LineBuilder {
+    let mut line = LineBuilder::default();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        line.push_coded(&rest[..start]);
+        let placeholder = rest[start..]
+            .find('}')
+            .map(|end| &rest[start..=start + end]);
+        let consumed = match placeholder {
+            Some("{prefix}") => {
+                line.push_coded(prefix);
+                "{prefix}".len()
+            }
+            Some("{suffix}") => {
+                line.push_coded(suffix);
+                "{suffix}".len()
+            }
+            Some("{name}") => {
+                line.push_plain(name);
+                "{name}".len()
+            }
+            Some("{message}") => {
+                line.push_plain(message);
+                "{message}".len()
+            }
+            _ => {
+                line.push_plain("{");
+                1
+            }
+        };
+        rest = &rest[start + consumed..];
+    }
+    line.push_coded(rest);
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::cache::PlayerIndex;
+
+    const FORMAT: &str = "{prefix}{name}{suffix}&r: {message}";
+
+    fn colored(color: NamedColor) -> Style {
+        Style {
+            color: Some(color),
+            ..Style::default()
+        }
+    }
+
+    fn parts(mut line: LineBuilder) -> Vec<(Style, String)> {
+        line.flush();
+        line.parts
+    }
+
+    fn segments(expected: &[(Style, &str)]) -> Vec<(Style, String)> {
+        expected
+            .iter()
+            .map(|(style, text)| (*style, text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        let line = render(FORMAT, "&c[Admin] ", "Steve", "&7*", "hi &a there");
+        assert_eq!(
+            parts(line),
+            segments(&[
+                (colored(NamedColor::Red), "[Admin] Steve"),
+                (colored(NamedColor::Gray), "*"),
+                // Codes typed by the player are shown as-is.
+                (Style::default(), ": hi &a there"),
+            ])
+        );
+    }
+
+    #[test]
+    fn unknown_placeholders_are_kept() {
+        let line = render("{rank} {name}: {message", "", "Steve", "", "hi");
+        assert_eq!(
+            parts(line),
+            segments(&[(Style::default(), "{rank} Steve: {message")])
+        );
+    }
+
+    #[test]
+    fn colors_clear_formatting() {
+        let bold = Style {
+            bold: true,
+            ..Style::default()
+        };
+        let line = render("&lloud &cred&r plain", "", "", "", "");
+        assert_eq!(
+            parts(line),
+            segments(&[
+                (bold, "loud "),
+                (colored(NamedColor::Red), "red"),
+                (Style::default(), " plain"),
+            ])
+        );
+    }
+
+    #[test]
+    fn player_meta_beats_the_heaviest_group() {
+        let meta = |key: &str, value: &str| {
+            Arc::new(HashMap::from([(key.to_string(), value.to_string())]))
+        };
+        let uuid = Uuid::from_u128(1);
+        let mut snapshot = Snapshot::default();
+        for (group, prefix) in [("admin", "[A] "), ("mod", "[M] ")] {
+            snapshot
+                .group_chains
+                .insert(group.to_string(), vec![group.to_string()]);
+            snapshot
+                .group_meta
+                .insert(group.to_string(), meta(META_PREFIX, prefix));
+        }
+        let player = |meta: &[(&str, &str)]| PlayerIndex {
+            groups: vec![("admin".to_string(), None), ("mod".to_string(), None)],
+            meta: meta
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            ..PlayerIndex::default()
+        };
+        snapshot.players.insert(uuid, Arc::new(player(&[])));
+        let line = chat_line("{prefix}{name}{suffix}", &snapshot, &uuid, "Steve", "");
+        assert_eq!(parts(line), segments(&[(Style::default(), "[A] Steve")]));
+
+        let own = player(&[(META_PREFIX, "[Owner] "), (META_SUFFIX, "!")]);
+        snapshot.players.insert(uuid, Arc::new(own));
+        let line = chat_line("{prefix}{name}{suffix}", &snapshot, &uuid, "Steve", "");
+        assert_eq!(
+            parts(line),
+            segments(&[(Style::default(), "[Owner] Steve!")])
+        );
+    }
+}
@@ -1,5 +1,8 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use arc_swap::ArcSwap;
+use pumpkin::command::args::message::MsgArgConsumer;
 use pumpkin::command::args::players::PlayersArgumentConsumer;
 use pumpkin::command::args::simple::SimpleArgConsumer;
 use pumpkin::command::args::{ConsumedArgs, FindArg};
@@ -11,13 +14,38 @@ use pumpkin::server::Server;
 use pumpkin_util::text::TextComponent;
 use tokio::sync::RwLock;
 
-use crate::store::PermissionStore;
+use crate::args::{
+    GroupArgumentConsumer, PermissionArgumentConsumer, SeenNodes, TargetArgumentConsumer,
+    find_targets,
+};
+use crate::backup::Backups;
+use crate::cache::{Decision, Snapshot};
+use crate::handler::gamemode_name;
+use crate::node::PermissionContext;
+use crate::page::{Entry, Paged};
+use crate::store::{PermissionStore, display_name};
+use crate::verbose::{Filter, Listener, Verbose};
+use crate::{diff, duration, node};
 
+const ARG_BACKUP: &str = "id";
+const ARG_DURATION: &str = "duration";
+const ARG_FALLBACK: &str = "fallback";
+const ARG_FILTER: &str = "filter";
 const ARG_GROUP_NAME: &str = "name";
+const ARG_KEY: &str = "key";
+const ARG_NEW_NAME: &str = "new";
+const ARG_PAGE: &str = "page";
+const ARG_PARENT: &str = "parent";
+const ARG_PARENTS: &str = "parents";
 const ARG_PERMISSION: &str = "permission";
+const ARG_PLAYER: &str = "player";
+const ARG_POSITION: &str = "position";
 const ARG_TARGET: &str = "target";
+const ARG_TRACK: &str = "track";
+const ARG_VALUE: &str = "value";
+const ARG_WEIGHT: &str = "weight";
 
-fn save_store(store: &PermissionStore) -> Result<(), CommandError> {
+fn save_store(store: &mut PermissionStore) -> Result<(), CommandError> {
     store
         .save()
         .map_err(|e| CommandError::CommandFailed(TextComponent::text(e)))
@@ -32,6 +60,80 @@ fn format_list(items: impl IntoIterator<Item = impl AsRef<str>>) -> String {
     }
 }
 
+/// Every entry that applies, sorted, each searching for where it's set when
+/// clicked.
+fn all_entries(permissions: HashMap<String, bool>) -> Vec<Entry> {
+    let mut permissions: Vec<_> = permissions.into_iter().collect();
+    permissions.sort();
+    permissions
+        .into_iter()
+        .map(|(node, granted)| {
+            let text = if granted {
+                node.clone()
+            } else {
+                format!("-{node}")
+            };
+            let (base, _) = node::split_qualifiers(&node);
+            Entry::permission(text, granted)
+                .hover("Click to find where it's set")
+                .run(format!("/seed search {base}"))
+        })
+        .collect()
+}
+
+fn temporary_entries(pd: &crate::store::PlayerData, now: u64) -> Vec<Entry> {
+    let lists = [
+        ("", &pd.temp_permissions),
+        ("-", &pd.temp_denied_permissions),
+        ("group ", &pd.temp_groups),
+    ];
+    let mut temporary: Vec<_> = lists
+        .into_iter()
+        .flat_map(|(label, map)| {
+            map.iter()
+                .filter(|(_, expires)| **expires > now)
+                .map(move |(entry, expires)| {
+                    let text = format!("{label}{entry} ({} left)", duration::format(expires - now));
+                    (text, label, entry)
+                })
+        })
+        .collect();
+    temporary.sort();
+    temporary
+        .into_iter()
+        .map(|(text, label, entry)| match label {
+            "group " => Entry::plain(text)
+                .hover(format!("Show group '{entry}'"))
+                .run(format!("/seed group info {entry}")),
+            _ => Entry::permission(text, label.is_empty()),
+        })
+        .collect()
+}
+
+/// Meta as `key=value`, sorted, each filling in `{unset} <key>` when clicked.
+fn meta_entries(meta: &HashMap<String, String>, unset: &str) -> Vec<Entry> {
+    let mut meta: Vec<_> = meta.iter().collect();
+    meta.sort();
+    meta.into_iter()
+        .map(|(key, value)| {
+            Entry::plain(format!("{key}={value}"))
+                .hover("Click to remove")
+                .suggest(format!("{unset} {key}"))
+        })
+        .collect()
+}
+
+/// The optional page number argument, 1 if absent.
+fn page_arg(args: &ConsumedArgs) -> Result<usize, String> {
+    let Ok(raw) = SimpleArgConsumer::find_arg(args, ARG_PAGE) else {
+        return Ok(1);
+    };
+    raw.parse::<usize>()
+        .ok()
+        .filter(|page| *page >= 1)
+        .ok_or_else(|| format!("Page must be a positive whole number, got '{raw}'"))
+}
+
 fn format_sorted_list(items: impl IntoIterator<Item = impl AsRef<str>>) -> String {
     let mut collected: Vec<_> = items.into_iter().map(|s| s.as_ref().to_string()).collect();
     collected.sort();
@@ -42,6 +144,23 @@ fn format_sorted_list(items: impl IntoIterator<Item = impl AsRef<str>>) -> Strin
     }
 }
 
+fn format_decision(decision: Option<Decision>) -> String {
+    match decision {
+        Some(Decision::Player { list, entry, .. }) => format!("'{entry}' in the player's {list}"),
+        Some(Decision::Group {
+            group,
+            source,
+            entry,
+        }) if group == source => format!("'{entry}' in group '{group}'"),
+        Some(Decision::Group {
+            group,
+            source,
+            entry,
+        }) => format!("'{entry}' in group '{source}', inherited by '{group}'"),
+        None => "no matching entry, falls through to Pumpkin's default".to_string(),
+    }
+}
+
 struct GroupCreateExecutor(Arc<RwLock<PermissionStore>>);
 
 impl CommandExecutor for GroupCreateExecutor {
@@ -63,14 +182,11 @@ impl CommandExecutor for GroupCreateExecutor {
                     .await;
                 return Ok(0);
             }
-            store.groups.insert(
-                name.to_string(),
-                crate::store::Group {
-                    permissions: Vec::new(),
-                    inheritance: Vec::new(),
-                },
-            );
-            save_store(&store)?;
+            store
+                .groups
+                .insert(name.to_string(), crate::store::Group::default());
+            store.refresh_group(name);
+            save_store(&mut store)?;
             sender
                 .send_message(TextComponent::text(format!("Created group '{name}'")))
                 .await;
@@ -79,7 +195,13 @@ impl CommandExecutor for GroupCreateExecutor {
     }
 }
 
-struct GroupDeleteExecutor(Arc<RwLock<PermissionStore>>);
+#[derive(Clone, Copy)]
+enum GroupDeleteOp {
+    Preview,
+    Confirm,
+}
+
+struct GroupDeleteExecutor(Arc<RwLock<PermissionStore>>, GroupDeleteOp);
 
 impl CommandExecutor for GroupDeleteExecutor {
     fn execute<'a>(
@@ -89,25 +211,116 @@ impl CommandExecutor for GroupDeleteExecutor {
         args: &'a ConsumedArgs<'a>,
     ) -> CommandResult<'a> {
         let store = self.0.clone();
+        let op = self.1;
         Box::pin(async move {
             let name = SimpleArgConsumer::find_arg(args, ARG_GROUP_NAME)?;
-            if name == "default" {
-                sender
-                    .send_message(TextComponent::text("Cannot delete the 'default' group"))
-                    .await;
-                return Ok(0);
+            let fallback = SimpleArgConsumer::find_arg(args, ARG_FALLBACK).ok();
+            let mut store = store.write().await;
+            // Groups nothing refers to are deleted right away; otherwise show
+            // what would change and wait for `confirm`.
+            if matches!(op, GroupDeleteOp::Preview) && store.groups.contains_key(name) {
+                let usage = store.group_usage(name);
+                if !usage.members.is_empty()
+                    || !usage.children.is_empty()
+                    || !usage.tracks.is_empty()
+                {
+                    let members = usage
+                        .members
+                        .iter()
+                        .map(|uuid| display_name(uuid, &store.players[uuid].username));
+                    let confirm = if usage.members.is_empty() {
+                        format!("/seed group delete {name} confirm")
+                    } else {
+                        format!("/seed group delete {name} confirm <fallback group>")
+                    };
+                    sender
+                        .send_message(TextComponent::text(format!(
+                            "Deleting group '{name}' would affect:\n  Members: {}\n  Inherited by: {}\n  Tracks: {}\nRun {confirm} to delete it{}",
+                            format_list(members),
+                            format_list(&usage.children),
+                            format_list(&usage.tracks),
+                            if usage.members.is_empty() {
+                                ""
+                            } else {
+                                ", moving its members to the fallback group"
+                            }
+                        )))
+                        .await;
+                    return Ok(0);
+                }
+            }
+            let usage = match store.delete_group(name, fallback) {
+                Ok(usage) => usage,
+                Err(e) => {
+                    sender.send_message(TextComponent::text(e)).await;
+                    return Ok(0);
+                }
+            };
+            save_store(&mut store)?;
+            let mut message = format!("Deleted group '{name}'");
+            if let Some(fallback) = fallback
+                && !usage.members.is_empty()
+            {
+                message.push_str(&format!(
+                    "; moved {} member(s) to '{fallback}'",
+                    usage.members.len()
+                ));
+            }
+            if !usage.children.is_empty() {
+                message.push_str(&format!(
+                    "; removed it from the parents of {}",
+                    format_list(&usage.children)
+                ));
             }
+            if !usage.tracks.is_empty() {
+                message.push_str(&format!(
+                    "; removed it from tracks {}",
+                    format_list(&usage.tracks)
+                ));
+            }
+            sender.send_message(TextComponent::text(message)).await;
+            Ok(1)
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum GroupCopyOp {
+    Rename,
+    Clone,
+}
+
+struct GroupCopyExecutor(Arc<RwLock<PermissionStore>>, GroupCopyOp);
+
+impl CommandExecutor for GroupCopyExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        let op = self.1;
+        Box::pin(async move {
+            let name = SimpleArgConsumer::find_arg(args, ARG_GROUP_NAME)?;
+            let new_name = SimpleArgConsumer::find_arg(args, ARG_NEW_NAME)?;
             let mut store = store.write().await;
-            if store.groups.remove(name).is_none() {
-                sender
-                    .send_message(TextComponent::text(format!("Group '{name}' not found")))
-                    .await;
+            let (result, message) = match op {
+                GroupCopyOp::Rename => (
+                    store.rename_group(name, new_name),
+                    format!("Renamed group '{name}' to '{new_name}'"),
+                ),
+                GroupCopyOp::Clone => (
+                    store.clone_group(name, new_name),
+                    format!("Cloned group '{name}' as '{new_name}'"),
+                ),
+            };
+            if let Err(e) = result {
+                sender.send_message(TextComponent::text(e)).await;
                 return Ok(0);
             }
-            save_store(&store)?;
-            sender
-                .send_message(TextComponent::text(format!("Deleted group '{name}'")))
-                .await;
+            save_store(&mut store)?;
+            sender.send_message(TextComponent::text(message)).await;
             Ok(1)
         })
     }
@@ -133,6 +346,12 @@ impl CommandExecutor for GroupPermExecutor {
         Box::pin(async move {
             let group_name = SimpleArgConsumer::find_arg(args, ARG_GROUP_NAME)?;
             let permission = SimpleArgConsumer::find_arg(args, ARG_PERMISSION)?;
+            if matches!(op, GroupPermOp::Add)
+                && let Err(e) = node::validate(permission)
+            {
+                sender.send_message(TextComponent::text(e)).await;
+                return Ok(0);
+            }
             let mut store = store.write().await;
             let Some(group) = store.groups.get_mut(group_name) else {
                 sender
@@ -154,7 +373,8 @@ impl CommandExecutor for GroupPermExecutor {
                         return Ok(0);
                     }
                     group.permissions.push(perm_str);
-                    save_store(&store)?;
+                    store.refresh_group(group_name);
+                    save_store(&mut store)?;
                     sender
                         .send_message(TextComponent::text(format!(
                             "Added permission '{permission}' to group '{group_name}'"
@@ -171,7 +391,8 @@ impl CommandExecutor for GroupPermExecutor {
                         return Ok(0);
                     };
                     group.permissions.remove(pos);
-                    save_store(&store)?;
+                    store.refresh_group(group_name);
+                    save_store(&mut store)?;
                     sender
                         .send_message(TextComponent::text(format!(
                             "Removed permission '{permission}' from group '{group_name}'"
@@ -184,6 +405,197 @@ impl CommandExecutor for GroupPermExecutor {
     }
 }
 
+#[derive(Clone, Copy)]
+enum GroupParentOp {
+    Add,
+    Remove,
+    Set,
+    Clear,
+}
+
+struct GroupParentExecutor(Arc<RwLock<PermissionStore>>, GroupParentOp);
+
+impl CommandExecutor for GroupParentExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        let op = self.1;
+        Box::pin(async move {
+            let group_name = SimpleArgConsumer::find_arg(args, ARG_GROUP_NAME)?;
+            let mut store = store.write().await;
+            let Some(group) = store.groups.get(group_name) else {
+                sender
+                    .send_message(TextComponent::text(format!(
+                        "Group '{group_name}' not found"
+                    )))
+                    .await;
+                return Ok(0);
+            };
+            let mut parents = group.inheritance.clone();
+            let message = match op {
+                GroupParentOp::Add => {
+                    let parent = SimpleArgConsumer::find_arg(args, ARG_PARENT)?;
+                    if parents.iter().any(|p| p == parent) {
+                        sender
+                            .send_message(TextComponent::text(format!(
+                                "Group '{group_name}' already inherits from '{parent}'"
+                            )))
+                            .await;
+                        return Ok(0);
+                    }
+                    parents.push(parent.to_string());
+                    format!("Group '{group_name}' now inherits from '{parent}'")
+                }
+                GroupParentOp::Remove => {
+                    let parent = SimpleArgConsumer::find_arg(args, ARG_PARENT)?;
+                    let Some(pos) = parents.iter().position(|p| p == parent) else {
+                        sender
+                            .send_message(TextComponent::text(format!(
+                                "Group '{group_name}' does not inherit from '{parent}'"
+                            )))
+                            .await;
+                        return Ok(0);
+                    };
+                    parents.remove(pos);
+                    format!("Group '{group_name}' no longer inherits from '{parent}'")
+                }
+                GroupParentOp::Set => {
+                    let list = MsgArgConsumer::find_arg(args, ARG_PARENTS)?;
+                    parents.clear();
+                    for parent in list.split([' ', ',']).filter(|p| !p.is_empty()) {
+                        if !parents.iter().any(|p| p == parent) {
+                            parents.push(parent.to_string());
+                        }
+                    }
+                    format!(
+                        "Set the parents of group '{group_name}' to {}",
+                        format_list(&parents)
+                    )
+                }
+                GroupParentOp::Clear => {
+                    parents.clear();
+                    format!("Group '{group_name}' no longer inherits from any group")
+                }
+            };
+            if let Err(e) = store.set_parents(group_name, parents) {
+                sender.send_message(TextComponent::text(e)).await;
+                return Ok(0);
+            }
+            save_store(&mut store)?;
+            let ancestors = format_list(store.group_chain(group_name).into_iter().skip(1));
+            sender
+                .send_message(TextComponent::text(format!(
+                    "{message}\n  Ancestors (nearest first): {ancestors}"
+                )))
+                .await;
+            Ok(1)
+        })
+    }
+}
+
+struct GroupWeightExecutor(Arc<RwLock<PermissionStore>>);
+
+impl CommandExecutor for GroupWeightExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        Box::pin(async move {
+            let group_name = SimpleArgConsumer::find_arg(args, ARG_GROUP_NAME)?;
+            let weight = SimpleArgConsumer::find_arg(args, ARG_WEIGHT)?;
+            let Ok(weight) = weight.parse::<i32>() else {
+                sender
+                    .send_message(TextComponent::text(format!(
+                        "Weight must be a whole number, got '{weight}'"
+                    )))
+                    .await;
+                return Ok(0);
+            };
+            let mut store = store.write().await;
+            let Some(group) = store.groups.get_mut(group_name) else {
+                sender
+                    .send_message(TextComponent::text(format!(
+                        "Group '{group_name}' not found"
+                    )))
+                    .await;
+                return Ok(0);
+            };
+            group.weight = weight;
+            store.refresh_group(group_name);
+            save_store(&mut store)?;
+            sender
+                .send_message(TextComponent::text(format!(
+                    "Set weight of group '{group_name}' to {weight}"
+                )))
+                .await;
+            Ok(1)
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum MetaOp {
+    Set,
+    Unset,
+}
+
+struct GroupMetaExecutor(Arc<RwLock<PermissionStore>>, MetaOp);
+
+impl CommandExecutor for GroupMetaExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        let op = self.1;
+        Box::pin(async move {
+            let group_name = SimpleArgConsumer::find_arg(args, ARG_GROUP_NAME)?;
+            let key = SimpleArgConsumer::find_arg(args, ARG_KEY)?;
+            let mut store = store.write().await;
+            let Some(group) = store.groups.get_mut(group_name) else {
+                sender
+                    .send_message(TextComponent::text(format!(
+                        "Group '{group_name}' not found"
+                    )))
+                    .await;
+                return Ok(0);
+            };
+            let message = match op {
+                MetaOp::Set => {
+                    let value = MsgArgConsumer::find_arg(args, ARG_VALUE)?;
+                    let message = format!("Set meta '{key}' of group '{group_name}' to '{value}'");
+                    group.meta.insert(key.to_string(), value);
+                    message
+                }
+                MetaOp::Unset => {
+                    if group.meta.remove(key).is_none() {
+                        sender
+                            .send_message(TextComponent::text(format!(
+                                "Group '{group_name}' has no meta '{key}'"
+                            )))
+                            .await;
+                        return Ok(0);
+                    }
+                    format!("Removed meta '{key}' from group '{group_name}'")
+                }
+            };
+            store.refresh_group(group_name);
+            save_store(&mut store)?;
+            sender.send_message(TextComponent::text(message)).await;
+            Ok(1)
+        })
+    }
+}
+
 struct GroupInfoExecutor(Arc<RwLock<PermissionStore>>);
 
 impl CommandExecutor for GroupInfoExecutor {
@@ -196,6 +608,8 @@ impl CommandExecutor for GroupInfoExecutor {
         let store = self.0.clone();
         Box::pin(async move {
             let name = SimpleArgConsumer::find_arg(args, ARG_GROUP_NAME)?;
+            let page =
+                page_arg(args).map_err(|e| CommandError::CommandFailed(TextComponent::text(e)))?;
             let store = store.read().await;
             let Some(group) = store.groups.get(name) else {
                 sender
@@ -203,13 +617,96 @@ impl CommandExecutor for GroupInfoExecutor {
                     .await;
                 return Ok(0);
             };
-            let perms = format_list(&group.permissions);
-            let inheritance = format_list(&group.inheritance);
-            let effective = format_sorted_list(store.resolve_group_permissions(name));
+            let ancestors = store.group_chain(name).into_iter().skip(1);
+            let output = Paged::new(format!("Group '{name}'"))
+                .field(
+                    "Weight",
+                    vec![
+                        Entry::plain(group.weight.to_string())
+                            .hover("Click to change")
+                            .suggest(format!("/seed group setweight {name} ")),
+                    ],
+                )
+                .field(
+                    "Inheritance",
+                    group.inheritance.iter().map(|p| Entry::group(p)).collect(),
+                )
+                .field(
+                    "Ancestors (nearest first)",
+                    ancestors.map(Entry::group).collect(),
+                )
+                .field(
+                    "Meta",
+                    meta_entries(&group.meta, &format!("/seed group unsetmeta {name}")),
+                )
+                .section(
+                    "Permissions",
+                    group
+                        .permissions
+                        .iter()
+                        .map(|entry| {
+                            Entry::permission(entry, node::parse(entry).1)
+                                .hover("Click to remove")
+                                .suggest(format!("/seed group removeperm {name} {entry}"))
+                        })
+                        .collect(),
+                )
+                .section(
+                    "All entries",
+                    all_entries(store.resolve_group_permissions(name)),
+                );
             sender
-                .send_message(TextComponent::text(format!(
-                    "Group '{name}':\n  Permissions: {perms}\n  Inheritance: {inheritance}\n  Effective: {effective}"
-                )))
+                .send_message(output.render(page, &format!("/seed group info {name}")))
+                .await;
+            Ok(1)
+        })
+    }
+}
+
+struct GroupMembersExecutor(Arc<RwLock<PermissionStore>>);
+
+impl CommandExecutor for GroupMembersExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        Box::pin(async move {
+            let name = SimpleArgConsumer::find_arg(args, ARG_GROUP_NAME)?;
+            let page =
+                page_arg(args).map_err(|e| CommandError::CommandFailed(TextComponent::text(e)))?;
+            let store = store.read().await;
+            if !store.groups.contains_key(name) {
+                sender
+                    .send_message(TextComponent::text(format!("Group '{name}' not found")))
+                    .await;
+                return Ok(0);
+            }
+            let now = duration::now();
+            let members = store
+                .group_members(name, now)
+                .into_iter()
+                .map(|(uuid, pd, via)| {
+                    let how = if via != name {
+                        format!("via '{via}'")
+                    } else if let Some(expires) = pd.temp_groups.get(name)
+                        && !pd.groups.iter().any(|g| g == name)
+                    {
+                        format!("temporary, {} left", duration::format(expires - now))
+                    } else {
+                        "direct".to_string()
+                    };
+                    let name = display_name(uuid, &pd.username);
+                    Entry::plain(format!("{name} ({how})"))
+                        .hover(format!("Show {name}'s info"))
+                        .run(format!("/seed player info {uuid}"))
+                })
+                .collect();
+            let output = Paged::new(format!("Group '{name}'")).section("Members", members);
+            sender
+                .send_message(output.render(page, &format!("/seed group members {name}")))
                 .await;
             Ok(1)
         })
@@ -242,9 +739,47 @@ impl CommandExecutor for GroupListExecutor {
     }
 }
 
-struct PlayerSetGroupExecutor(Arc<RwLock<PermissionStore>>);
+#[derive(Clone, Copy)]
+enum PlayerGroupOp {
+    Set,
+    Add,
+    Remove,
+}
+
+/// Removes `group_name` from a player's permanent or temporary groups.
+/// Returns the reply and whether anything changed.
+fn remove_group(
+    pd: Option<&mut crate::store::PlayerData>,
+    name: &str,
+    group_name: &str,
+) -> (String, bool) {
+    let not_member = || (format!("{name} is not in group '{group_name}'"), false);
+    let Some(pd) = pd else {
+        return not_member();
+    };
+    match pd.groups.iter().position(|g| g == group_name) {
+        None if pd.temp_groups.remove(group_name).is_some() => {
+            (format!("Removed {name} from group '{group_name}'"), true)
+        }
+        None => not_member(),
+        Some(pos) => {
+            pd.groups.remove(pos);
+            if pd.groups.is_empty() {
+                pd.groups.push("default".to_string());
+                (
+                    format!("Removed {name} from group '{group_name}'; they are back in 'default'"),
+                    true,
+                )
+            } else {
+                (format!("Removed {name} from group '{group_name}'"), true)
+            }
+        }
+    }
+}
+
+struct PlayerGroupExecutor(Arc<RwLock<PermissionStore>>, PlayerGroupOp);
 
-impl CommandExecutor for PlayerSetGroupExecutor {
+impl CommandExecutor for PlayerGroupExecutor {
     fn execute<'a>(
         &'a self,
         sender: &'a CommandSender,
@@ -252,11 +787,12 @@ impl CommandExecutor for PlayerSetGroupExecutor {
         args: &'a ConsumedArgs<'a>,
     ) -> CommandResult<'a> {
         let store = self.0.clone();
+        let op = self.1;
         Box::pin(async move {
-            let players = PlayersArgumentConsumer::find_arg(args, ARG_TARGET)?;
             let group_name = SimpleArgConsumer::find_arg(args, ARG_GROUP_NAME)?;
             let mut store = store.write().await;
-            if !store.groups.contains_key(group_name) {
+            let targets = find_targets(args, ARG_TARGET, &store)?;
+            if !matches!(op, PlayerGroupOp::Remove) && !store.groups.contains_key(group_name) {
                 sender
                     .send_message(TextComponent::text(format!(
                         "Group '{group_name}' not found"
@@ -264,18 +800,42 @@ impl CommandExecutor for PlayerSetGroupExecutor {
                     .await;
                 return Ok(0);
             }
-            for player in players {
-                let pd =
-                    store.get_or_create_player(player.gameprofile.id, &player.gameprofile.name);
-                pd.group = group_name.to_string();
-                sender
-                    .send_message(TextComponent::text(format!(
-                        "Set {}'s group to '{group_name}'",
-                        player.gameprofile.name
-                    )))
-                    .await;
+            let mut refreshed = Vec::new();
+            for target in &targets {
+                let name = &target.name;
+                let (message, changed) = match op {
+                    PlayerGroupOp::Set => {
+                        let pd = store.get_or_create_player(target.uuid, &target.username);
+                        pd.groups = vec![group_name.to_string()];
+                        (format!("Set {name}'s group to '{group_name}'"), true)
+                    }
+                    PlayerGroupOp::Add => {
+                        let already = store
+                            .players
+                            .get(&target.uuid)
+                            .map_or(group_name == "default", |pd| {
+                                pd.groups.iter().any(|g| g == group_name)
+                            });
+                        if already {
+                            (format!("{name} is already in group '{group_name}'"), false)
+                        } else {
+                            let pd = store.get_or_create_player(target.uuid, &target.username);
+                            pd.groups.push(group_name.to_string());
+                            pd.temp_groups.remove(group_name);
+                            (format!("Added {name} to group '{group_name}'"), true)
+                        }
+                    }
+                    PlayerGroupOp::Remove => {
+                        remove_group(store.players.get_mut(&target.uuid), name, group_name)
+                    }
+                };
+                if changed {
+                    refreshed.push(target.uuid);
+                }
+                sender.send_message(TextComponent::text(message)).await;
             }
-            save_store(&store)?;
+            store.refresh_players(refreshed);
+            save_store(&mut store)?;
             Ok(1)
         })
     }
@@ -297,6 +857,13 @@ impl PlayerPermOp {
         }
     }
 
+    fn temp_map(self, pd: &mut crate::store::PlayerData) -> &mut HashMap<String, u64> {
+        match self {
+            Self::AddExtra | Self::RemoveExtra => &mut pd.temp_permissions,
+            Self::Deny | Self::Undeny => &mut pd.temp_denied_permissions,
+        }
+    }
+
     fn is_add(self) -> bool {
         matches!(self, Self::AddExtra | Self::Deny)
     }
@@ -332,59 +899,535 @@ impl CommandExecutor for PlayerPermExecutor {
         let store = self.0.clone();
         let op = self.1;
         Box::pin(async move {
-            let players = PlayersArgumentConsumer::find_arg(args, ARG_TARGET)?;
             let permission = SimpleArgConsumer::find_arg(args, ARG_PERMISSION)?;
+            if op.is_add()
+                && let Err(e) = node::validate(permission)
+            {
+                sender.send_message(TextComponent::text(e)).await;
+                return Ok(0);
+            }
             let perm_str = permission.to_string();
             let mut store = store.write().await;
-            for player in players {
-                let name = &player.gameprofile.name;
-                let pd = store.get_or_create_player(player.gameprofile.id, name);
-                let list = op.target_list(pd);
-                if op.is_add() {
-                    if list.contains(&perm_str) {
-                        sender
-                            .send_message(TextComponent::text(format!(
-                                "{name} {} '{permission}'",
-                                op.already_msg()
-                            )))
-                            .await;
-                    } else {
+            let targets = find_targets(args, ARG_TARGET, &store)?;
+            let mut refreshed = Vec::new();
+            for target in &targets {
+                let name = &target.name;
+                let pd = if op.is_add() {
+                    Some(store.get_or_create_player(target.uuid, &target.username))
+                } else {
+                    store.players.get_mut(&target.uuid)
+                };
+                let changed = pd.is_some_and(|pd| {
+                    let list = op.target_list(pd);
+                    if op.is_add() {
+                        if list.contains(&perm_str) {
+                            return false;
+                        }
                         list.push(perm_str.clone());
-                        sender
-                            .send_message(TextComponent::text(format!(
-                                "{} '{permission}' for {name}",
-                                op.action_past()
-                            )))
-                            .await;
+                        // A permanent entry supersedes a temporary one.
+                        op.temp_map(pd).remove(&perm_str);
+                        return true;
                     }
+                    match list.iter().position(|p| p == &perm_str) {
+                        Some(pos) => {
+                            list.remove(pos);
+                            true
+                        }
+                        None => op.temp_map(pd).remove(&perm_str).is_some(),
+                    }
+                });
+                let message = if changed {
+                    refreshed.push(target.uuid);
+                    format!("{} '{permission}' for {name}", op.action_past())
                 } else {
-                    let Some(pos) = list.iter().position(|p| p == &perm_str) else {
+                    format!("{name} {} '{permission}'", op.already_msg())
+                };
+                sender.send_message(TextComponent::text(message)).await;
+            }
+            store.refresh_players(refreshed);
+            save_store(&mut store)?;
+            Ok(1)
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum PlayerTempOp {
+    Permission,
+    Deny,
+    Group,
+}
+
+impl PlayerTempOp {
+    fn target_map(self, pd: &mut crate::store::PlayerData) -> &mut HashMap<String, u64> {
+        match self {
+            Self::Permission => &mut pd.temp_permissions,
+            Self::Deny => &mut pd.temp_denied_permissions,
+            Self::Group => &mut pd.temp_groups,
+        }
+    }
+
+    fn value_arg(self) -> &'static str {
+        match self {
+            Self::Permission | Self::Deny => ARG_PERMISSION,
+            Self::Group => ARG_GROUP_NAME,
+        }
+    }
+
+    fn action_past(self) -> &'static str {
+        match self {
+            Self::Permission => "Granted permission",
+            Self::Deny => "Denied permission",
+            Self::Group => "Added group",
+        }
+    }
+}
+
+struct PlayerTempExecutor(Arc<RwLock<PermissionStore>>, PlayerTempOp);
+
+impl CommandExecutor for PlayerTempExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        let op = self.1;
+        Box::pin(async move {
+            let value = SimpleArgConsumer::find_arg(args, op.value_arg())?;
+            if !matches!(op, PlayerTempOp::Group)
+                && let Err(e) = node::validate(value)
+            {
+                sender.send_message(TextComponent::text(e)).await;
+                return Ok(0);
+            }
+            let raw_duration = SimpleArgConsumer::find_arg(args, ARG_DURATION)?;
+            let Some(secs) = duration::parse(raw_duration) else {
+                sender
+                    .send_message(TextComponent::text(format!(
+                        "Invalid duration '{raw_duration}', expected e.g. 30m, 12h or 1d12h"
+                    )))
+                    .await;
+                return Ok(0);
+            };
+            let mut store = store.write().await;
+            let targets = find_targets(args, ARG_TARGET, &store)?;
+            if matches!(op, PlayerTempOp::Group) && !store.groups.contains_key(value) {
+                sender
+                    .send_message(TextComponent::text(format!("Group '{value}' not found")))
+                    .await;
+                return Ok(0);
+            }
+            let expires = duration::now().saturating_add(secs);
+            let length = duration::format(secs);
+            let mut refreshed = Vec::new();
+            for target in &targets {
+                let name = &target.name;
+                let pd = store.get_or_create_player(target.uuid, &target.username);
+                op.target_map(pd).insert(value.to_string(), expires);
+                refreshed.push(target.uuid);
+                sender
+                    .send_message(TextComponent::text(format!(
+                        "{} '{value}' for {name} for {length}",
+                        op.action_past()
+                    )))
+                    .await;
+            }
+            store.refresh_players(refreshed);
+            save_store(&mut store)?;
+            Ok(1)
+        })
+    }
+}
+
+struct PlayerMetaExecutor(Arc<RwLock<PermissionStore>>, MetaOp);
+
+impl CommandExecutor for PlayerMetaExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        let op = self.1;
+        Box::pin(async move {
+            let key = SimpleArgConsumer::find_arg(args, ARG_KEY)?;
+            let value = match op {
+                MetaOp::Set => Some(MsgArgConsumer::find_arg(args, ARG_VALUE)?),
+                MetaOp::Unset => None,
+            };
+            let mut store = store.write().await;
+            let targets = find_targets(args, ARG_TARGET, &store)?;
+            let mut refreshed = Vec::new();
+            for target in &targets {
+                let name = &target.name;
+                let (message, changed) = match &value {
+                    Some(value) => {
+                        let pd = store.get_or_create_player(target.uuid, &target.username);
+                        pd.meta.insert(key.to_string(), value.clone());
+                        (format!("Set meta '{key}' of {name} to '{value}'"), true)
+                    }
+                    None => {
+                        let removed = store
+                            .players
+                            .get_mut(&target.uuid)
+                            .is_some_and(|pd| pd.meta.remove(key).is_some());
+                        match removed {
+                            true => (format!("Removed meta '{key}' from {name}"), true),
+                            false => (format!("{name} has no meta '{key}'"), false),
+                        }
+                    }
+                };
+                if changed {
+                    refreshed.push(target.uuid);
+                }
+                sender.send_message(TextComponent::text(message)).await;
+            }
+            store.refresh_players(refreshed);
+            save_store(&mut store)?;
+            Ok(1)
+        })
+    }
+}
+
+struct PlayerInfoExecutor(Arc<RwLock<PermissionStore>>);
+
+impl CommandExecutor for PlayerInfoExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        Box::pin(async move {
+            let page =
+                page_arg(args).map_err(|e| CommandError::CommandFailed(TextComponent::text(e)))?;
+            let store = store.read().await;
+            let targets = find_targets(args, ARG_TARGET, &store)?;
+            for target in &targets {
+                let uuid = target.uuid;
+                let name = &target.name;
+                let Some(pd) = store.players.get(&uuid) else {
+                    sender
+                        .send_message(TextComponent::text(format!(
+                            "Player '{name}' ({uuid}): groups=default (no custom data)"
+                        )))
+                        .await;
+                    continue;
+                };
+                let groups = store
+                    .sort_by_weight(&pd.groups)
+                    .into_iter()
+                    .map(Entry::group);
+                let affix = |value: Option<&str>| value.map(Entry::plain).into_iter().collect();
+                let extras = pd.extra_permissions.iter().map(|entry| {
+                    Entry::permission(entry, true)
+                        .hover("Click to remove")
+                        .suggest(format!("/seed player removeperm {uuid} {entry}"))
+                });
+                let denied = pd.denied_permissions.iter().map(|entry| {
+                    Entry::permission(entry, false)
+                        .hover("Click to remove the denial")
+                        .suggest(format!("/seed player undeny {uuid} {entry}"))
+                });
+                let output = Paged::new(format!("Player '{name}'"))
+                    .field(
+                        "UUID",
+                        vec![
+                            Entry::plain(uuid.to_string())
+                                .hover("Click to copy")
+                                .copy(uuid.to_string()),
+                        ],
+                    )
+                    .field("Groups", groups.collect())
+                    .field("Prefix", affix(store.prefix(&uuid)))
+                    .field("Suffix", affix(store.suffix(&uuid)))
+                    .field(
+                        "Meta",
+                        meta_entries(&pd.meta, &format!("/seed player unsetmeta {uuid}")),
+                    )
+                    .section("Extra", extras.collect())
+                    .section("Denied", denied.collect())
+                    .section("Temporary", temporary_entries(pd, duration::now()))
+                    .section(
+                        "All entries",
+                        all_entries(store.resolve_player_permissions(pd)),
+                    );
+                sender
+                    .send_message(output.render(page, &format!("/seed player info {uuid}")))
+                    .await;
+            }
+            Ok(1)
+        })
+    }
+}
+
+struct PlayerCheckExecutor(Arc<RwLock<PermissionStore>>);
+
+impl CommandExecutor for PlayerCheckExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        Box::pin(async move {
+            let players = PlayersArgumentConsumer::find_arg(args, ARG_TARGET)?;
+            let permission = SimpleArgConsumer::find_arg(args, ARG_PERMISSION)?;
+            let snapshot = store.read().await.snapshot();
+            for player in players {
+                let world = player.world();
+                let dimension = world.dimension.minecraft_name;
+                let gamemode = gamemode_name(player.gamemode.load());
+                let ctx = PermissionContext {
+                    dimension: Some(dimension),
+                    gamemode: Some(gamemode),
+                };
+                let decision = snapshot.trace_permission(&player.gameprofile.id, permission, &ctx);
+                let result = match decision.map(|d| d.granted()) {
+                    Some(true) => "granted",
+                    Some(false) => "denied",
+                    None => "not set by Seed",
+                };
+                let reason = format_decision(decision);
+                sender
+                    .send_message(TextComponent::text(format!(
+                        "'{}' {permission}: {result} (in {dimension}, {gamemode})\n  Decided by: {reason}",
+                        player.gameprofile.name
+                    )))
+                    .await;
+            }
+            Ok(1)
+        })
+    }
+}
+
+struct TrackCreateExecutor(Arc<RwLock<PermissionStore>>);
+
+impl CommandExecutor for TrackCreateExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        Box::pin(async move {
+            let name = SimpleArgConsumer::find_arg(args, ARG_TRACK)?;
+            let mut store = store.write().await;
+            if store.tracks.contains_key(name) {
+                sender
+                    .send_message(TextComponent::text(format!(
+                        "Track '{name}' already exists"
+                    )))
+                    .await;
+                return Ok(0);
+            }
+            store
+                .tracks
+                .insert(name.to_string(), crate::store::Track::default());
+            store.track_changed(name);
+            save_store(&mut store)?;
+            sender
+                .send_message(TextComponent::text(format!("Created track '{name}'")))
+                .await;
+            Ok(1)
+        })
+    }
+}
+
+struct TrackDeleteExecutor(Arc<RwLock<PermissionStore>>);
+
+impl CommandExecutor for TrackDeleteExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        Box::pin(async move {
+            let name = SimpleArgConsumer::find_arg(args, ARG_TRACK)?;
+            let mut store = store.write().await;
+            if store.tracks.remove(name).is_none() {
+                sender
+                    .send_message(TextComponent::text(format!("Track '{name}' not found")))
+                    .await;
+                return Ok(0);
+            }
+            store.track_changed(name);
+            save_store(&mut store)?;
+            sender
+                .send_message(TextComponent::text(format!("Deleted track '{name}'")))
+                .await;
+            Ok(1)
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum TrackGroupOp {
+    Append,
+    Insert,
+    Remove,
+}
+
+struct TrackGroupExecutor(Arc<RwLock<PermissionStore>>, TrackGroupOp);
+
+impl CommandExecutor for TrackGroupExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        let op = self.1;
+        Box::pin(async move {
+            let track_name = SimpleArgConsumer::find_arg(args, ARG_TRACK)?;
+            let group_name = SimpleArgConsumer::find_arg(args, ARG_GROUP_NAME)?;
+            let mut store = store.write().await;
+            if !matches!(op, TrackGroupOp::Remove) && !store.groups.contains_key(group_name) {
+                sender
+                    .send_message(TextComponent::text(format!(
+                        "Group '{group_name}' not found"
+                    )))
+                    .await;
+                return Ok(0);
+            }
+            let Some(track) = store.tracks.get_mut(track_name) else {
+                sender
+                    .send_message(TextComponent::text(format!(
+                        "Track '{track_name}' not found"
+                    )))
+                    .await;
+                return Ok(0);
+            };
+            let existing = track.groups.iter().position(|g| g == group_name);
+            let message = match (op, existing) {
+                (TrackGroupOp::Append | TrackGroupOp::Insert, Some(_)) => {
+                    sender
+                        .send_message(TextComponent::text(format!(
+                            "Track '{track_name}' already contains group '{group_name}'"
+                        )))
+                        .await;
+                    return Ok(0);
+                }
+                (TrackGroupOp::Remove, None) => {
+                    sender
+                        .send_message(TextComponent::text(format!(
+                            "Track '{track_name}' does not contain group '{group_name}'"
+                        )))
+                        .await;
+                    return Ok(0);
+                }
+                (TrackGroupOp::Append, None) => {
+                    track.groups.push(group_name.to_string());
+                    format!("Appended group '{group_name}' to track '{track_name}'")
+                }
+                (TrackGroupOp::Insert, None) => {
+                    let position = SimpleArgConsumer::find_arg(args, ARG_POSITION)?;
+                    let len = track.groups.len();
+                    let Some(index) = position
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|p| (1..=len + 1).contains(p))
+                    else {
                         sender
                             .send_message(TextComponent::text(format!(
-                                "{name} {} '{permission}'",
-                                op.already_msg()
+                                "Position must be between 1 and {}, got '{position}'",
+                                len + 1
                             )))
                             .await;
-                        continue;
+                        return Ok(0);
                     };
-                    list.remove(pos);
-                    sender
-                        .send_message(TextComponent::text(format!(
-                            "{} '{permission}' for {name}",
-                            op.action_past()
-                        )))
-                        .await;
+                    track.groups.insert(index - 1, group_name.to_string());
+                    format!(
+                        "Inserted group '{group_name}' into track '{track_name}' at position {index}"
+                    )
                 }
-            }
-            save_store(&store)?;
+                (TrackGroupOp::Remove, Some(pos)) => {
+                    track.groups.remove(pos);
+                    format!("Removed group '{group_name}' from track '{track_name}'")
+                }
+            };
+            store.track_changed(track_name);
+            save_store(&mut store)?;
+            sender.send_message(TextComponent::text(message)).await;
+            Ok(1)
+        })
+    }
+}
+
+struct TrackInfoExecutor(Arc<RwLock<PermissionStore>>);
+
+impl CommandExecutor for TrackInfoExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        Box::pin(async move {
+            let name = SimpleArgConsumer::find_arg(args, ARG_TRACK)?;
+            let store = store.read().await;
+            let Some(track) = store.tracks.get(name) else {
+                sender
+                    .send_message(TextComponent::text(format!("Track '{name}' not found")))
+                    .await;
+                return Ok(0);
+            };
+            let ladder = if track.groups.is_empty() {
+                "(none)".to_string()
+            } else {
+                track.groups.join(" -> ")
+            };
+            sender
+                .send_message(TextComponent::text(format!("Track '{name}': {ladder}")))
+                .await;
+            Ok(1)
+        })
+    }
+}
+
+struct TrackListExecutor(Arc<RwLock<PermissionStore>>);
+
+impl CommandExecutor for TrackListExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        _args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            let names = format_sorted_list(store.tracks.keys());
+            sender
+                .send_message(TextComponent::text(format!(
+                    "Tracks ({}): {names}",
+                    store.tracks.len()
+                )))
+                .await;
             Ok(1)
         })
     }
 }
 
-struct PlayerInfoExecutor(Arc<RwLock<PermissionStore>>);
+#[derive(Clone, Copy)]
+enum PlayerTrackOp {
+    Promote,
+    Demote,
+}
 
-impl CommandExecutor for PlayerInfoExecutor {
+struct PlayerTrackExecutor(Arc<RwLock<PermissionStore>>, PlayerTrackOp);
+
+impl CommandExecutor for PlayerTrackExecutor {
     fn execute<'a>(
         &'a self,
         sender: &'a CommandSender,
@@ -392,45 +1435,145 @@ impl CommandExecutor for PlayerInfoExecutor {
         args: &'a ConsumedArgs<'a>,
     ) -> CommandResult<'a> {
         let store = self.0.clone();
+        let op = self.1;
         Box::pin(async move {
-            let players = PlayersArgumentConsumer::find_arg(args, ARG_TARGET)?;
-            let store = store.read().await;
-            for player in players {
-                let uuid = player.gameprofile.id;
-                let name = &player.gameprofile.name;
-                let Some(pd) = store.players.get(&uuid) else {
-                    sender
-                        .send_message(TextComponent::text(format!(
-                            "Player '{name}' ({uuid}): group=default (no custom data)"
-                        )))
-                        .await;
-                    continue;
+            let track_name = SimpleArgConsumer::find_arg(args, ARG_TRACK)?;
+            let mut store = store.write().await;
+            let targets = find_targets(args, ARG_TARGET, &store)?;
+            let mut changed = false;
+            for target in &targets {
+                let uuid = target.uuid;
+                let name = &target.name;
+                let result = match op {
+                    PlayerTrackOp::Promote => store
+                        .promote(uuid, &target.username, track_name)
+                        .map(|(from, to)| match from {
+                            Some(from) => format!("Promoted {name} from '{from}' to '{to}'"),
+                            None => format!("Promoted {name} to '{to}'"),
+                        }),
+                    PlayerTrackOp::Demote => store
+                        .demote(uuid, &target.username, track_name)
+                        .map(|(from, to)| format!("Demoted {name} from '{from}' to '{to}'")),
                 };
-                let extras = format_list(&pd.extra_permissions);
-                let denied = format_list(&pd.denied_permissions);
-                let mut effective: Vec<_> = store
-                    .resolve_group_permissions(&pd.group)
-                    .into_iter()
-                    .collect();
-                for ep in &pd.extra_permissions {
-                    if !effective.contains(ep) {
-                        effective.push(ep.clone());
-                    }
-                }
-                let effective = format_sorted_list(effective);
-                sender
-                    .send_message(TextComponent::text(format!(
-                        "Player '{name}' ({uuid}):\n  Group: {}\n  Extra: {extras}\n  Denied: {denied}\n  Effective: {effective}",
-                        pd.group
-                    )))
-                    .await;
+                changed |= result.is_ok();
+                let message = result.unwrap_or_else(|e| e);
+                sender.send_message(TextComponent::text(message)).await;
+            }
+            if changed {
+                save_store(&mut store)?;
+            }
+            Ok(1)
+        })
+    }
+}
+
+/// How many changes a reload lists before summarizing the rest.
+const MAX_DIFF_LINES: usize = 30;
+
+fn format_diff(lines: Vec<String>) -> String {
+    if lines.is_empty() {
+        return "no changes".to_string();
+    }
+    let more = lines.len().saturating_sub(MAX_DIFF_LINES);
+    let mut shown: Vec<String> = lines.into_iter().take(MAX_DIFF_LINES).collect();
+    if more > 0 {
+        shown.push(format!("... and {more} more"));
+    }
+    format!("\n  {}", shown.join("\n  "))
+}
+
+enum ReloadOp {
+    Apply,
+    DryRun,
+}
+
+/// Every group and player entry for a node `query` matches or that matches
+/// `query`, so both `minecraft:command.ban` and `minecraft:command.*` find
+/// `minecraft:command.*` entries. Qualifiers are ignored on both sides.
+fn search_entries(store: &PermissionStore, query: &str, now: u64) -> Vec<Entry> {
+    let query = query.strip_prefix('-').unwrap_or(query);
+    let (query, _) = node::split_qualifiers(query);
+    let matches = |entry: &str| {
+        let (node, _) = node::split_qualifiers(node::parse(entry).0);
+        node::covers(node, query) || node::covers(query, node)
+    };
+    let mut found = Vec::new();
+
+    let mut groups: Vec<_> = store.groups.iter().collect();
+    groups.sort_by_key(|(name, _)| *name);
+    for (name, group) in groups {
+        for entry in group.permissions.iter().filter(|e| matches(e)) {
+            found.push(
+                Entry::permission(format!("group '{name}': {entry}"), node::parse(entry).1)
+                    .hover(format!("Show group '{name}'"))
+                    .run(format!("/seed group info {name}")),
+            );
+        }
+    }
+
+    let mut players: Vec<_> = store.players.iter().collect();
+    players.sort_by(|a, b| a.1.username.cmp(&b.1.username));
+    for (uuid, pd) in players {
+        let name = display_name(uuid, &pd.username);
+        let player = |text: String, granted: bool| {
+            Entry::permission(text, granted)
+                .hover(format!("Show {name}'s info"))
+                .run(format!("/seed player info {uuid}"))
+        };
+        for entry in pd.extra_permissions.iter().filter(|e| matches(e)) {
+            found.push(player(format!("player {name}: {entry}"), true));
+        }
+        for entry in pd.denied_permissions.iter().filter(|e| matches(e)) {
+            found.push(player(format!("player {name}: -{entry}"), false));
+        }
+        let temporary = [
+            ("", &pd.temp_permissions),
+            ("-", &pd.temp_denied_permissions),
+        ];
+        for (sign, entries) in temporary {
+            let mut entries: Vec<_> = entries
+                .iter()
+                .filter(|(entry, expires)| **expires > now && matches(entry))
+                .collect();
+            entries.sort();
+            for (entry, expires) in entries {
+                let text = format!(
+                    "player {name}: {sign}{entry} ({} left)",
+                    duration::format(expires - now)
+                );
+                found.push(player(text, sign.is_empty()));
             }
+        }
+    }
+    found
+}
+
+struct SearchExecutor(Arc<RwLock<PermissionStore>>);
+
+impl CommandExecutor for SearchExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        Box::pin(async move {
+            let permission = SimpleArgConsumer::find_arg(args, ARG_PERMISSION)?;
+            let page =
+                page_arg(args).map_err(|e| CommandError::CommandFailed(TextComponent::text(e)))?;
+            let store = store.read().await;
+            let found = search_entries(&store, permission, duration::now());
+            let output = Paged::new(format!("Search '{permission}'")).section("Matches", found);
+            sender
+                .send_message(output.render(page, &format!("/seed search {permission}")))
+                .await;
             Ok(1)
         })
     }
 }
 
-struct ReloadExecutor(Arc<RwLock<PermissionStore>>);
+struct ReloadExecutor(Arc<RwLock<PermissionStore>>, ReloadOp);
 
 impl CommandExecutor for ReloadExecutor {
     fn execute<'a>(
@@ -443,21 +1586,41 @@ impl CommandExecutor for ReloadExecutor {
         Box::pin(async move {
             let mut store = store.write().await;
             let data_folder = store.data_folder.clone();
-            match PermissionStore::load(data_folder) {
-                Ok(new_store) => {
-                    *store = new_store;
-                    sender
-                        .send_message(TextComponent::text("Seed configuration reloaded"))
-                        .await;
-                    Ok(1)
-                }
+            let dry_run = matches!(self.1, ReloadOp::DryRun);
+            // A dry run must not touch the files, not even to finish a save.
+            let recovered = if dry_run {
+                Ok(())
+            } else {
+                store.storage.recover()
+            };
+            let new_store = match recovered
+                .and_then(|()| PermissionStore::read(data_folder, store.storage.clone()))
+            {
+                Ok(new_store) => new_store,
                 Err(e) => {
                     sender
                         .send_message(TextComponent::text(format!("Reload failed: {e}")))
                         .await;
-                    Ok(0)
+                    return Ok(0);
                 }
+            };
+            let mut changes = format_diff(diff::describe(&store, &new_store));
+            for problem in new_store.inheritance_problems() {
+                changes.push_str(&format!("\n  Warning: {problem}"));
             }
+            let message = if dry_run {
+                if store.storage.interrupted_save() {
+                    changes.push_str(
+                        "\n  Warning: interrupted save pending; reloading finishes it first, so the result may differ",
+                    );
+                }
+                format!("Reload would apply: {changes}")
+            } else {
+                store.replace(new_store);
+                format!("Seed configuration reloaded: {changes}")
+            };
+            sender.send_message(TextComponent::text(message)).await;
+            Ok(1)
         })
     }
 }
@@ -473,8 +1636,8 @@ impl CommandExecutor for SaveExecutor {
     ) -> CommandResult<'a> {
         let store = self.0.clone();
         Box::pin(async move {
-            let store = store.read().await;
-            match store.save() {
+            let mut store = store.write().await;
+            match store.save_all() {
                 Ok(()) => {
                     sender
                         .send_message(TextComponent::text("Seed configuration saved"))
@@ -492,7 +1655,130 @@ impl CommandExecutor for SaveExecutor {
     }
 }
 
-pub fn build_command_tree(store: Arc<RwLock<PermissionStore>>) -> CommandTree {
+enum VerboseOp {
+    On,
+    Record,
+    Off,
+}
+
+struct VerboseExecutor(Arc<Verbose>, VerboseOp);
+
+impl CommandExecutor for VerboseExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let verbose = self.0.clone();
+        Box::pin(async move {
+            let listener = match sender {
+                CommandSender::Player(player) => Listener::Player(player.clone()),
+                _ => Listener::Console,
+            };
+            let message = match self.1 {
+                VerboseOp::On | VerboseOp::Record => {
+                    // `*` watches every player while still allowing a filter.
+                    let player = SimpleArgConsumer::find_arg(args, ARG_PLAYER)
+                        .ok()
+                        .filter(|p| *p != "*");
+                    let node = SimpleArgConsumer::find_arg(args, ARG_FILTER).ok();
+                    let filter = Filter {
+                        player: player.map(str::to_string),
+                        node: node.map(str::to_string),
+                    };
+                    let record = matches!(self.1, VerboseOp::Record);
+                    let watching = format!(
+                        "{} checking {}",
+                        player.unwrap_or("every player"),
+                        node.map_or("any permission".to_string(), |n| format!(
+                            "permissions containing '{n}'"
+                        ))
+                    );
+                    match verbose.start(listener, filter, record).await {
+                        Ok(Some(path)) => {
+                            format!("Recording {watching} to {}", path.display())
+                        }
+                        Ok(None) => format!("Verbose mode on for {watching}"),
+                        Err(e) => return Err(CommandError::CommandFailed(TextComponent::text(e))),
+                    }
+                }
+                VerboseOp::Off => match verbose.stop(&listener).await {
+                    Ok(Some(path)) => format!("Recording saved to {}", path.display()),
+                    Ok(None) => "Verbose mode off".to_string(),
+                    Err(e) => {
+                        sender.send_message(TextComponent::text(e)).await;
+                        return Ok(0);
+                    }
+                },
+            };
+            sender.send_message(TextComponent::text(message)).await;
+            Ok(1)
+        })
+    }
+}
+
+enum BackupOp {
+    List,
+    Create,
+    Restore,
+}
+
+struct BackupExecutor(Arc<RwLock<PermissionStore>>, Arc<Backups>, BackupOp);
+
+impl CommandExecutor for BackupExecutor {
+    fn execute<'a>(
+        &'a self,
+        sender: &'a CommandSender,
+        _server: &'a Server,
+        args: &'a ConsumedArgs<'a>,
+    ) -> CommandResult<'a> {
+        let store = self.0.clone();
+        let backups = self.1.clone();
+        Box::pin(async move {
+            let failed = |e: String| CommandError::CommandFailed(TextComponent::text(e));
+            let message = match self.2 {
+                BackupOp::List => {
+                    let now = duration::now();
+                    let list = backups.list().map_err(failed)?;
+                    let entries = list.iter().map(|(id, created)| {
+                        format!(
+                            "{id} ({} ago)",
+                            duration::format(now.saturating_sub(*created))
+                        )
+                    });
+                    format!("Backups, newest first: {}", format_list(entries))
+                }
+                BackupOp::Create => {
+                    let store = store.read().await;
+                    let id = backups.create(&store).map_err(failed)?;
+                    format!("Created backup {id}")
+                }
+                BackupOp::Restore => {
+                    let id = SimpleArgConsumer::find_arg(args, ARG_BACKUP)?;
+                    let data = backups.read(id).map_err(failed)?;
+                    let mut store = store.write().await;
+                    // Keep what is being replaced, so the restore can be undone.
+                    let previous = backups.create(&store).map_err(failed)?;
+                    store.restore(data).map_err(failed)?;
+                    format!(
+                        "Restored backup {id}. The previous data was saved as backup {previous}"
+                    )
+                }
+            };
+            sender.send_message(TextComponent::text(message)).await;
+            Ok(1)
+        })
+    }
+}
+
+pub fn build_command_tree(
+    store: Arc<RwLock<PermissionStore>>,
+    verbose: Arc<Verbose>,
+    backups: Arc<Backups>,
+    snapshot: Arc<ArcSwap<Snapshot>>,
+    seen: Arc<SeenNodes>,
+) -> CommandTree {
     CommandTree::new(["seed"], "Seed permission plugin commands")
         .then(
             literal("group")
@@ -504,30 +1790,139 @@ pub fn build_command_tree(store: Arc<RwLock<PermissionStore>>) -> CommandTree {
                 )
                 .then(
                     literal("delete").then(
-                        argument(ARG_GROUP_NAME, SimpleArgConsumer)
-                            .execute(GroupDeleteExecutor(store.clone())),
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone()))
+                            .execute(GroupDeleteExecutor(store.clone(), GroupDeleteOp::Preview))
+                            .then(
+                                literal("confirm")
+                                    .execute(GroupDeleteExecutor(
+                                        store.clone(),
+                                        GroupDeleteOp::Confirm,
+                                    ))
+                                    .then(
+                                        argument(
+                                            ARG_FALLBACK,
+                                            GroupArgumentConsumer(store.clone()),
+                                        )
+                                        .execute(
+                                            GroupDeleteExecutor(
+                                                store.clone(),
+                                                GroupDeleteOp::Confirm,
+                                            ),
+                                        ),
+                                    ),
+                            ),
                     ),
                 )
                 .then(
                     literal("addperm").then(
-                        argument(ARG_GROUP_NAME, SimpleArgConsumer).then(
-                            argument(ARG_PERMISSION, SimpleArgConsumer)
-                                .execute(GroupPermExecutor(store.clone(), GroupPermOp::Add)),
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone())).then(
+                            argument(
+                                ARG_PERMISSION,
+                                PermissionArgumentConsumer(snapshot.clone(), seen.clone()),
+                            )
+                            .execute(GroupPermExecutor(store.clone(), GroupPermOp::Add)),
                         ),
                     ),
                 )
                 .then(
                     literal("removeperm").then(
-                        argument(ARG_GROUP_NAME, SimpleArgConsumer).then(
-                            argument(ARG_PERMISSION, SimpleArgConsumer)
-                                .execute(GroupPermExecutor(store.clone(), GroupPermOp::Remove)),
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone())).then(
+                            argument(
+                                ARG_PERMISSION,
+                                PermissionArgumentConsumer(snapshot.clone(), seen.clone()),
+                            )
+                            .execute(GroupPermExecutor(store.clone(), GroupPermOp::Remove)),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("rename").then(
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone())).then(
+                            argument(ARG_NEW_NAME, SimpleArgConsumer)
+                                .execute(GroupCopyExecutor(store.clone(), GroupCopyOp::Rename)),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("clone").then(
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone())).then(
+                            argument(ARG_NEW_NAME, SimpleArgConsumer)
+                                .execute(GroupCopyExecutor(store.clone(), GroupCopyOp::Clone)),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("addparent").then(
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone())).then(
+                            argument(ARG_PARENT, GroupArgumentConsumer(store.clone()))
+                                .execute(GroupParentExecutor(store.clone(), GroupParentOp::Add)),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("removeparent").then(
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone())).then(
+                            argument(ARG_PARENT, GroupArgumentConsumer(store.clone()))
+                                .execute(GroupParentExecutor(store.clone(), GroupParentOp::Remove)),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("setparents").then(
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone()))
+                            .execute(GroupParentExecutor(store.clone(), GroupParentOp::Clear))
+                            .then(
+                                argument(ARG_PARENTS, MsgArgConsumer).execute(GroupParentExecutor(
+                                    store.clone(),
+                                    GroupParentOp::Set,
+                                )),
+                            ),
+                    ),
+                )
+                .then(
+                    literal("setweight").then(
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone())).then(
+                            argument(ARG_WEIGHT, SimpleArgConsumer)
+                                .execute(GroupWeightExecutor(store.clone())),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("setmeta").then(
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone())).then(
+                            argument(ARG_KEY, SimpleArgConsumer).then(
+                                argument(ARG_VALUE, MsgArgConsumer)
+                                    .execute(GroupMetaExecutor(store.clone(), MetaOp::Set)),
+                            ),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("unsetmeta").then(
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone())).then(
+                            argument(ARG_KEY, SimpleArgConsumer)
+                                .execute(GroupMetaExecutor(store.clone(), MetaOp::Unset)),
                         ),
                     ),
                 )
                 .then(
                     literal("info").then(
-                        argument(ARG_GROUP_NAME, SimpleArgConsumer)
-                            .execute(GroupInfoExecutor(store.clone())),
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone()))
+                            .execute(GroupInfoExecutor(store.clone()))
+                            .then(
+                                argument(ARG_PAGE, SimpleArgConsumer)
+                                    .execute(GroupInfoExecutor(store.clone())),
+                            ),
+                    ),
+                )
+                .then(
+                    literal("members").then(
+                        argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone()))
+                            .execute(GroupMembersExecutor(store.clone()))
+                            .then(
+                                argument(ARG_PAGE, SimpleArgConsumer)
+                                    .execute(GroupMembersExecutor(store.clone())),
+                            ),
                     ),
                 )
                 .then(literal("list").execute(GroupListExecutor(store.clone()))),
@@ -536,52 +1931,284 @@ pub fn build_command_tree(store: Arc<RwLock<PermissionStore>>) -> CommandTree {
             literal("player")
                 .then(
                     literal("setgroup").then(
-                        argument(ARG_TARGET, PlayersArgumentConsumer).then(
-                            argument(ARG_GROUP_NAME, SimpleArgConsumer)
-                                .execute(PlayerSetGroupExecutor(store.clone())),
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone()))
+                                .execute(PlayerGroupExecutor(store.clone(), PlayerGroupOp::Set)),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("addgroup").then(
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone()))
+                                .execute(PlayerGroupExecutor(store.clone(), PlayerGroupOp::Add)),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("removegroup").then(
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone()))
+                                .execute(PlayerGroupExecutor(store.clone(), PlayerGroupOp::Remove)),
                         ),
                     ),
                 )
                 .then(
                     literal("addperm").then(
-                        argument(ARG_TARGET, PlayersArgumentConsumer)
-                            .then(argument(ARG_PERMISSION, SimpleArgConsumer).execute(
-                                PlayerPermExecutor(store.clone(), PlayerPermOp::AddExtra),
-                            )),
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(
+                                ARG_PERMISSION,
+                                PermissionArgumentConsumer(snapshot.clone(), seen.clone()),
+                            )
+                            .execute(PlayerPermExecutor(store.clone(), PlayerPermOp::AddExtra)),
+                        ),
                     ),
                 )
                 .then(
                     literal("removeperm").then(
-                        argument(ARG_TARGET, PlayersArgumentConsumer).then(
-                            argument(ARG_PERMISSION, SimpleArgConsumer).execute(
-                                PlayerPermExecutor(store.clone(), PlayerPermOp::RemoveExtra),
-                            ),
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(
+                                ARG_PERMISSION,
+                                PermissionArgumentConsumer(snapshot.clone(), seen.clone()),
+                            )
+                            .execute(PlayerPermExecutor(store.clone(), PlayerPermOp::RemoveExtra)),
                         ),
                     ),
                 )
                 .then(
                     literal("deny").then(
-                        argument(ARG_TARGET, PlayersArgumentConsumer).then(
-                            argument(ARG_PERMISSION, SimpleArgConsumer)
-                                .execute(PlayerPermExecutor(store.clone(), PlayerPermOp::Deny)),
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(
+                                ARG_PERMISSION,
+                                PermissionArgumentConsumer(snapshot.clone(), seen.clone()),
+                            )
+                            .execute(PlayerPermExecutor(store.clone(), PlayerPermOp::Deny)),
                         ),
                     ),
                 )
                 .then(
                     literal("undeny").then(
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(
+                                ARG_PERMISSION,
+                                PermissionArgumentConsumer(snapshot.clone(), seen.clone()),
+                            )
+                            .execute(PlayerPermExecutor(store.clone(), PlayerPermOp::Undeny)),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("addtemp").then(
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(
+                                ARG_PERMISSION,
+                                PermissionArgumentConsumer(snapshot.clone(), seen.clone()),
+                            )
+                            .then(
+                                argument(ARG_DURATION, SimpleArgConsumer).execute(
+                                    PlayerTempExecutor(store.clone(), PlayerTempOp::Permission),
+                                ),
+                            ),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("tempdeny").then(
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(
+                                ARG_PERMISSION,
+                                PermissionArgumentConsumer(snapshot.clone(), seen.clone()),
+                            )
+                            .then(
+                                argument(ARG_DURATION, SimpleArgConsumer)
+                                    .execute(PlayerTempExecutor(store.clone(), PlayerTempOp::Deny)),
+                            ),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("tempgroup").then(
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone())).then(
+                                argument(ARG_DURATION, SimpleArgConsumer).execute(
+                                    PlayerTempExecutor(store.clone(), PlayerTempOp::Group),
+                                ),
+                            ),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("promote").then(
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(ARG_TRACK, SimpleArgConsumer).execute(PlayerTrackExecutor(
+                                store.clone(),
+                                PlayerTrackOp::Promote,
+                            )),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("demote").then(
+                        argument(ARG_TARGET, TargetArgumentConsumer)
+                            .then(argument(ARG_TRACK, SimpleArgConsumer).execute(
+                                PlayerTrackExecutor(store.clone(), PlayerTrackOp::Demote),
+                            )),
+                    ),
+                )
+                .then(
+                    literal("setmeta").then(
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(ARG_KEY, SimpleArgConsumer).then(
+                                argument(ARG_VALUE, MsgArgConsumer)
+                                    .execute(PlayerMetaExecutor(store.clone(), MetaOp::Set)),
+                            ),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("unsetmeta").then(
+                        argument(ARG_TARGET, TargetArgumentConsumer).then(
+                            argument(ARG_KEY, SimpleArgConsumer)
+                                .execute(PlayerMetaExecutor(store.clone(), MetaOp::Unset)),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("check").then(
                         argument(ARG_TARGET, PlayersArgumentConsumer).then(
-                            argument(ARG_PERMISSION, SimpleArgConsumer)
-                                .execute(PlayerPermExecutor(store.clone(), PlayerPermOp::Undeny)),
+                            argument(
+                                ARG_PERMISSION,
+                                PermissionArgumentConsumer(snapshot.clone(), seen.clone()),
+                            )
+                            .execute(PlayerCheckExecutor(store.clone())),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("info").then(
+                        argument(ARG_TARGET, TargetArgumentConsumer)
+                            .execute(PlayerInfoExecutor(store.clone()))
+                            .then(
+                                argument(ARG_PAGE, SimpleArgConsumer)
+                                    .execute(PlayerInfoExecutor(store.clone())),
+                            ),
+                    ),
+                ),
+        )
+        .then(
+            literal("track")
+                .then(
+                    literal("create").then(
+                        argument(ARG_TRACK, SimpleArgConsumer)
+                            .execute(TrackCreateExecutor(store.clone())),
+                    ),
+                )
+                .then(
+                    literal("delete").then(
+                        argument(ARG_TRACK, SimpleArgConsumer)
+                            .execute(TrackDeleteExecutor(store.clone())),
+                    ),
+                )
+                .then(
+                    literal("append").then(
+                        argument(ARG_TRACK, SimpleArgConsumer).then(
+                            argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone()))
+                                .execute(TrackGroupExecutor(store.clone(), TrackGroupOp::Append)),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("insert").then(
+                        argument(ARG_TRACK, SimpleArgConsumer).then(
+                            argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone())).then(
+                                argument(ARG_POSITION, SimpleArgConsumer).execute(
+                                    TrackGroupExecutor(store.clone(), TrackGroupOp::Insert),
+                                ),
+                            ),
+                        ),
+                    ),
+                )
+                .then(
+                    literal("remove").then(
+                        argument(ARG_TRACK, SimpleArgConsumer).then(
+                            argument(ARG_GROUP_NAME, GroupArgumentConsumer(store.clone()))
+                                .execute(TrackGroupExecutor(store.clone(), TrackGroupOp::Remove)),
                         ),
                     ),
                 )
                 .then(
                     literal("info").then(
-                        argument(ARG_TARGET, PlayersArgumentConsumer)
-                            .execute(PlayerInfoExecutor(store.clone())),
+                        argument(ARG_TRACK, SimpleArgConsumer)
+                            .execute(TrackInfoExecutor(store.clone())),
                     ),
+                )
+                .then(literal("list").execute(TrackListExecutor(store.clone()))),
+        )
+        .then(
+            literal("reload")
+                .execute(ReloadExecutor(store.clone(), ReloadOp::Apply))
+                .then(
+                    literal("--dry-run").execute(ReloadExecutor(store.clone(), ReloadOp::DryRun)),
                 ),
         )
-        .then(literal("reload").execute(ReloadExecutor(store.clone())))
-        .then(literal("save").execute(SaveExecutor(store)))
+        .then(literal("save").execute(SaveExecutor(store.clone())))
+        .then(
+            literal("search").then(
+                argument(
+                    ARG_PERMISSION,
+                    PermissionArgumentConsumer(snapshot.clone(), seen.clone()),
+                )
+                .execute(SearchExecutor(store.clone()))
+                .then(argument(ARG_PAGE, SimpleArgConsumer).execute(SearchExecutor(store.clone()))),
+            ),
+        )
+        .then(
+            literal("backup")
+                .then(literal("list").execute(BackupExecutor(
+                    store.clone(),
+                    backups.clone(),
+                    BackupOp::List,
+                )))
+                .then(literal("create").execute(BackupExecutor(
+                    store.clone(),
+                    backups.clone(),
+                    BackupOp::Create,
+                )))
+                .then(literal("restore").then(
+                    argument(ARG_BACKUP, SimpleArgConsumer).execute(BackupExecutor(
+                        store,
+                        backups,
+                        BackupOp::Restore,
+                    )),
+                )),
+        )
+        .then(
+            literal("verbose")
+                .then(
+                    literal("on")
+                        .execute(VerboseExecutor(verbose.clone(), VerboseOp::On))
+                        .then(
+                            argument(ARG_PLAYER, SimpleArgConsumer)
+                                .execute(VerboseExecutor(verbose.clone(), VerboseOp::On))
+                                .then(
+                                    argument(ARG_FILTER, SimpleArgConsumer)
+                                        .execute(VerboseExecutor(verbose.clone(), VerboseOp::On)),
+                                ),
+                        ),
+                )
+                .then(
+                    literal("record")
+                        .execute(VerboseExecutor(verbose.clone(), VerboseOp::Record))
+                        .then(
+                            argument(ARG_PLAYER, SimpleArgConsumer)
+                                .execute(VerboseExecutor(verbose.clone(), VerboseOp::Record))
+                                .then(
+                                    argument(ARG_FILTER, SimpleArgConsumer).execute(
+                                        VerboseExecutor(verbose.clone(), VerboseOp::Record),
+                                    ),
+                                ),
+                        ),
+                )
+                .then(literal("off").execute(VerboseExecutor(verbose, VerboseOp::Off))),
+        )
 }
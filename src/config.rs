@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub chat: ChatConfig,
+    pub storage: StorageConfig,
+    pub backup: BackupConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Whether backups are made automatically every `interval`.
+    pub enabled: bool,
+    /// How often to back up, e.g. `6h`.
+    pub interval: String,
+    /// How many backups to keep, automatic or not. Older ones are deleted.
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: "6h".to_string(),
+            keep: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub backend: Backend,
+    /// Whether to reload automatically when the TOML files are edited.
+    pub auto_reload: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// `groups.toml`, `players.toml` and `tracks.toml`.
+    #[default]
+    Toml,
+    /// A single `seed.db` SQLite database.
+    Sqlite,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatConfig {
+    /// Whether Seed rewrites chat messages using `format`. Off by default, so
+    /// installing Seed doesn't take over chat another plugin may be formatting.
+    pub enabled: bool,
+    /// Chat line template. `{prefix}`, `{name}`, `{suffix}` and `{message}` are
+    /// substituted, and `&` color codes in the template, prefix and suffix are
+    /// applied. Codes typed by players in their message are shown as-is.
+    pub format: String,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            format: "{prefix}{name}{suffix}&r: {message}".to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads `config.toml`, writing the defaults on first run.
+    pub fn load(data_folder: &Path) -> Result<Self, String> {
+        fs::create_dir_all(data_folder)
+            .map_err(|e| format!("Failed to create data folder: {e}"))?;
+        let path = data_folder.join("config.toml");
+        if path.exists() {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read config.toml: {e}"))?;
+            return toml::from_str(&content)
+                .map_err(|e| format!("Failed to parse config.toml: {e}"));
+        }
+        let config = Self::default();
+        let content = toml::to_string_pretty(&config)
+            .map_err(|e| format!("Failed to serialize config: {e}"))?;
+        fs::write(&path, content).map_err(|e| format!("Failed to write config.toml: {e}"))?;
+        Ok(config)
+    }
+}
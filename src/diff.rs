@@ -0,0 +1,226 @@
+//! Describes what changed between two versions of the store, e.g. before and
+//! after a reload.
+
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+
+use crate::store::{Group, PermissionStore, PlayerData};
+
+/// One line per change, groups first, then players and tracks. Empty if
+/// nothing changed.
+pub fn describe(old: &PermissionStore, new: &PermissionStore) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let (added, removed, kept) = compare_keys(&old.groups, &new.groups);
+    for name in added {
+        lines.push(format!("+ group '{name}'"));
+    }
+    for name in removed {
+        lines.push(format!("- group '{name}'"));
+    }
+    for name in kept {
+        describe_group(&mut lines, name, &old.groups[name], &new.groups[name]);
+    }
+
+    let (added, removed, kept) = compare_keys(&old.players, &new.players);
+    for uuid in added {
+        lines.push(format!("+ player {}", new.players[uuid].username));
+    }
+    for uuid in removed {
+        lines.push(format!("- player {}", old.players[uuid].username));
+    }
+    for uuid in kept {
+        describe_player(&mut lines, &old.players[uuid], &new.players[uuid]);
+    }
+
+    let (added, removed, kept) = compare_keys(&old.tracks, &new.tracks);
+    for name in added {
+        lines.push(format!("+ track '{name}'"));
+    }
+    for name in removed {
+        lines.push(format!("- track '{name}'"));
+    }
+    for name in kept {
+        let (old, new) = (&old.tracks[name].groups, &new.tracks[name].groups);
+        if old != new {
+            lines.push(format!(
+                "~ track '{name}': {} -> {}",
+                old.join(" > "),
+                new.join(" > ")
+            ));
+        }
+    }
+
+    lines
+}
+
+fn describe_group(lines: &mut Vec<String>, name: &str, old: &Group, new: &Group) {
+    let (added, removed) = compare_lists(&old.permissions, &new.permissions);
+    for permission in added {
+        lines.push(format!("~ group '{name}': + {permission}"));
+    }
+    for permission in removed {
+        lines.push(format!("~ group '{name}': - {permission}"));
+    }
+    let (added, removed) = compare_lists(&old.inheritance, &new.inheritance);
+    for parent in added {
+        lines.push(format!("~ group '{name}': + parent '{parent}'"));
+    }
+    for parent in removed {
+        lines.push(format!("~ group '{name}': - parent '{parent}'"));
+    }
+    if old.weight != new.weight {
+        lines.push(format!(
+            "~ group '{name}': weight {} -> {}",
+            old.weight, new.weight
+        ));
+    }
+    if old.meta != new.meta {
+        lines.push(format!("~ group '{name}': meta changed"));
+    }
+}
+
+fn describe_player(lines: &mut Vec<String>, old: &PlayerData, new: &PlayerData) {
+    let name = &new.username;
+    let (added, removed) = compare_lists(&old.groups, &new.groups);
+    for group in added {
+        lines.push(format!("~ player {name}: + group '{group}'"));
+    }
+    for group in removed {
+        lines.push(format!("~ player {name}: - group '{group}'"));
+    }
+    let (added, removed, _) = compare_keys(&old.temp_groups, &new.temp_groups);
+    for group in added {
+        lines.push(format!("~ player {name}: + temporary group '{group}'"));
+    }
+    for group in removed {
+        lines.push(format!("~ player {name}: - temporary group '{group}'"));
+    }
+    // Individual permissions and meta are summarized, the groups are what
+    // hand edits usually get wrong.
+    let rest_changed = old.extra_permissions != new.extra_permissions
+        || old.denied_permissions != new.denied_permissions
+        || old.temp_permissions != new.temp_permissions
+        || old.temp_denied_permissions != new.temp_denied_permissions
+        || old.meta != new.meta;
+    if rest_changed {
+        lines.push(format!("~ player {name}: permissions or meta changed"));
+    }
+}
+
+/// Sorted keys only in `new`, only in `old`, and in both.
+fn compare_keys<'a, K: Ord + Hash, V>(
+    old: &'a HashMap<K, V>,
+    new: &'a HashMap<K, V>,
+) -> (Vec<&'a K>, Vec<&'a K>, Vec<&'a K>) {
+    let old_keys: BTreeSet<&K> = old.keys().collect();
+    let new_keys: BTreeSet<&K> = new.keys().collect();
+    (
+        new_keys.difference(&old_keys).copied().collect(),
+        old_keys.difference(&new_keys).copied().collect(),
+        old_keys.intersection(&new_keys).copied().collect(),
+    )
+}
+
+/// Entries only in `new` and only in `old`, sorted.
+fn compare_lists<'a>(old: &'a [String], new: &'a [String]) -> (Vec<&'a str>, Vec<&'a str>) {
+    let old: BTreeSet<&str> = old.iter().map(String::as_str).collect();
+    let new: BTreeSet<&str> = new.iter().map(String::as_str).collect();
+    (
+        new.difference(&old).copied().collect(),
+        old.difference(&new).copied().collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::storage::TomlStorage;
+
+    /// A store from `groups.toml`-style groups and players keyed by UUID.
+    fn store(groups: &str, players: &str) -> PermissionStore {
+        let players: HashMap<String, PlayerData> = toml::from_str(players).unwrap();
+        let players = players
+            .into_iter()
+            .map(|(uuid, pd)| (Uuid::parse_str(&uuid).unwrap(), pd))
+            .collect();
+        PermissionStore::new(
+            PathBuf::new(),
+            Arc::new(TomlStorage::new(PathBuf::new())),
+            toml::from_str(groups).unwrap(),
+            players,
+            HashMap::new(),
+        )
+    }
+
+    const STEVE: &str = "00000000-0000-0000-0000-000000000001";
+    const ALEX: &str = "00000000-0000-0000-0000-000000000002";
+
+    #[test]
+    fn unchanged_stores_have_no_diff() {
+        let groups = "[default]\npermissions = [\"minecraft:command.help\"]\n";
+        let players = format!("[\"{STEVE}\"]\nusername = \"Steve\"\n");
+        assert!(describe(&store(groups, &players), &store(groups, &players)).is_empty());
+    }
+
+    #[test]
+    fn groups_added_removed_and_changed() {
+        let old = store(
+            "[default]\n[old]\n[mod]\npermissions = [\"a:b\", \"a:c\"]\ninheritance = [\"default\"]\n",
+            "",
+        );
+        let new = store(
+            "[default]\n[new]\n[mod]\npermissions = [\"a:c\", \"a:d\"]\nweight = 5\n\
+             [mod.meta]\nprefix = \"[M]\"\n",
+            "",
+        );
+        assert_eq!(
+            describe(&old, &new),
+            [
+                "+ group 'new'",
+                "- group 'old'",
+                "~ group 'mod': + a:d",
+                "~ group 'mod': - a:b",
+                "~ group 'mod': - parent 'default'",
+                "~ group 'mod': weight 0 -> 5",
+                "~ group 'mod': meta changed",
+            ]
+        );
+    }
+
+    #[test]
+    fn players_added_removed_and_changed() {
+        let old = store(
+            "[default]\n[mod]\n",
+            &format!(
+                "[\"{STEVE}\"]\nusername = \"Steve\"\ngroups = [\"default\"]\n\
+                 [\"{ALEX}\"]\nusername = \"Alex\"\n"
+            ),
+        );
+        let new = store(
+            "[default]\n[mod]\n",
+            &format!(
+                "[\"{STEVE}\"]\nusername = \"Steve\"\ngroups = [\"mod\"]\n\
+                 extra_permissions = [\"a:b\"]\n\
+                 [\"{STEVE}\".temp_groups]\nvip = 100\n\
+                 [\"00000000-0000-0000-0000-000000000003\"]\nusername = \"Herobrine\"\n"
+            ),
+        );
+        assert_eq!(
+            describe(&old, &new),
+            [
+                "+ player Herobrine",
+                "- player Alex",
+                "~ player Steve: + group 'mod'",
+                "~ player Steve: - group 'default'",
+                "~ player Steve: + temporary group 'vip'",
+                "~ player Steve: permissions or meta changed",
+            ]
+        );
+    }
+}
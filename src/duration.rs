@@ -0,0 +1,110 @@
+//! Human-readable durations (`30m`, `12h`, `1d12h`) and the clock that
+//! temporary entries are measured against.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const UNITS: [(char, u64); 5] = [
+    ('w', 7 * 24 * 60 * 60),
+    ('d', 24 * 60 * 60),
+    ('h', 60 * 60),
+    ('m', 60),
+    ('s', 1),
+];
+
+/// Current unix time in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Parses a duration made of `<number><unit>` pairs, where unit is one of
+/// `w`, `d`, `h`, `m` or `s`. Returns the total in seconds, or `None` if the
+/// input is malformed or zero.
+pub fn parse(input: &str) -> Option<u64> {
+    let mut total: u64 = 0;
+    let mut amount: Option<u64> = None;
+    for c in input.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            amount = Some(
+                amount
+                    .unwrap_or(0)
+                    .checked_mul(10)?
+                    .checked_add(digit.into())?,
+            );
+            continue;
+        }
+        let (_, unit) = UNITS
+            .iter()
+            .find(|(name, _)| *name == c.to_ascii_lowercase())?;
+        total = total.checked_add(amount.take()?.checked_mul(*unit)?)?;
+    }
+    // A trailing number without a unit is ambiguous, so reject it.
+    if amount.is_some() || total == 0 {
+        return None;
+    }
+    Some(total)
+}
+
+/// Formats seconds as e.g. `1d 12h 5m`.
+pub fn format(mut secs: u64) -> String {
+    let mut parts = Vec::new();
+    for (name, unit) in UNITS {
+        if secs >= unit {
+            parts.push(format!("{}{name}", secs / unit));
+            secs %= unit;
+        }
+    }
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_accepts_unit_pairs() {
+        let cases = [
+            ("30s", 30),
+            ("30m", 30 * 60),
+            ("12h", 12 * 60 * 60),
+            ("1d12h", 36 * 60 * 60),
+            ("2w", 14 * 24 * 60 * 60),
+            ("1H30M", 90 * 60),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse(input), Some(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn parse_rejects_malformed_input() {
+        let cases = [
+            "",
+            "12",
+            "1d12",
+            "0s",
+            "0d0h",
+            "h",
+            "1x",
+            "-1h",
+            "1.5h",
+            "99999999999999999999s",
+            "100000000000000w",
+            "18446744073709551615s1s",
+        ];
+        for input in cases {
+            assert_eq!(parse(input), None, "{input}");
+        }
+    }
+
+    #[test]
+    fn format_splits_into_units() {
+        assert_eq!(format(0), "0s");
+        assert_eq!(format(36 * 60 * 60 + 5 * 60), "1d 12h 5m");
+    }
+}
@@ -1,14 +1,34 @@
 use std::sync::Arc;
 
+use arc_swap::ArcSwap;
+use pumpkin::plugin::api::events::player::player_join::PlayerJoinEvent;
 use pumpkin::plugin::api::events::player::player_permission_check::PlayerPermissionCheckEvent;
-use pumpkin::plugin::{BoxFuture, EventHandler};
+use pumpkin::plugin::{BoxFuture, Context, EventHandler};
 use pumpkin::server::Server;
+use pumpkin_util::GameMode;
 use tokio::sync::RwLock;
 
+use crate::args::SeenNodes;
+use crate::cache::Snapshot;
+use crate::node::PermissionContext;
 use crate::store::PermissionStore;
+use crate::verbose::Verbose;
 
+/// Answers permission checks from the latest published snapshot, so checks
+/// never wait on commands holding the store's write lock.
 pub struct SeedPermissionHandler {
-    pub store: Arc<RwLock<PermissionStore>>,
+    pub snapshot: Arc<ArcSwap<Snapshot>>,
+    pub verbose: Arc<Verbose>,
+    pub seen: Arc<SeenNodes>,
+}
+
+pub fn gamemode_name(gamemode: GameMode) -> &'static str {
+    match gamemode {
+        GameMode::Survival => "survival",
+        GameMode::Creative => "creative",
+        GameMode::Adventure => "adventure",
+        GameMode::Spectator => "spectator",
+    }
 }
 
 impl EventHandler<PlayerPermissionCheckEvent> for SeedPermissionHandler {
@@ -18,12 +38,56 @@ impl EventHandler<PlayerPermissionCheckEvent> for SeedPermissionHandler {
         event: &'a mut PlayerPermissionCheckEvent,
     ) -> BoxFuture<'a, ()> {
         Box::pin(async move {
-            let store = self.store.read().await;
-            if let Some(result) =
-                store.check_permission(&event.player.gameprofile.id, &event.permission)
-            {
+            self.seen.observe(&event.permission);
+            let world = event.player.world();
+            let ctx = PermissionContext {
+                dimension: Some(world.dimension.minecraft_name),
+                gamemode: Some(gamemode_name(event.player.gamemode.load())),
+            };
+            let result = self.snapshot.load().check_permission(
+                &event.player.gameprofile.id,
+                &event.permission,
+                &ctx,
+            );
+            if let Some(result) = result {
                 event.result = result;
             }
+            if self.verbose.is_active() {
+                self.verbose
+                    .log(
+                        &event.player,
+                        &event.permission,
+                        event.result,
+                        result.is_some(),
+                    )
+                    .await;
+            }
+        })
+    }
+}
+
+/// Keeps stored usernames current, so players set up by UUID get their name
+/// when they first join, and renamed players can be found by their new name.
+pub struct SeedJoinHandler {
+    pub context: Arc<Context>,
+    pub store: Arc<RwLock<PermissionStore>>,
+}
+
+impl EventHandler<PlayerJoinEvent> for SeedJoinHandler {
+    fn handle<'a>(
+        &'a self,
+        _server: &'a Arc<Server>,
+        event: &'a PlayerJoinEvent,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let profile = &event.player.gameprofile;
+            let mut store = self.store.write().await;
+            if store.record_username(profile.id, &profile.name)
+                && let Err(e) = store.save()
+            {
+                self.context
+                    .log(format!("Failed to save {}'s username: {e}", profile.name));
+            }
         })
     }
 }
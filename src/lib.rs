@@ -1,29 +1,61 @@
 #![allow(clippy::async_yields_async, clippy::new_without_default)]
 
+mod args;
+mod atomic;
+mod backup;
+mod cache;
+mod chat;
 mod commands;
+mod config;
+mod diff;
+mod duration;
 mod handler;
-mod store;
+// Public for the benches.
+pub mod node;
+mod page;
+mod sqlite;
+pub mod storage;
+pub mod store;
+mod tasks;
+mod verbose;
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use pumpkin::plugin::Context;
 use pumpkin_api_macros::{plugin_impl, plugin_method};
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 
 use store::PermissionStore;
 
 static mut STORE: Option<Arc<RwLock<PermissionStore>>> = None;
+static mut TASKS: Option<Vec<JoinHandle<()>>> = None;
 
 #[plugin_method]
 fn on_load(&mut self, server: Arc<Context>) -> Result<(), String> {
     let data_folder = server.get_data_folder();
-    let store = PermissionStore::load(data_folder)?;
+    let config = config::Config::load(&data_folder)?;
+    let backup_interval = duration::parse(&config.backup.interval)
+        .ok_or_else(|| format!("Invalid backup interval '{}'", config.backup.interval))?;
+    let storage = storage::open(config.storage.backend, &data_folder)?;
+    let mut store = PermissionStore::load(data_folder.clone(), storage)?;
+    // Writes the default group on first run.
+    store.save()?;
+    for problem in store.inheritance_problems() {
+        server.log(format!("Warning: {problem}"));
+    }
+    let snapshot = store.snapshot_handle();
     let store = Arc::new(RwLock::new(store));
 
     unsafe { STORE = Some(store.clone()) };
 
+    let verbose = Arc::new(verbose::Verbose::new(server.clone(), data_folder.clone()));
+    let seen = Arc::new(args::SeenNodes::default());
     let handler = Arc::new(handler::SeedPermissionHandler {
-        store: store.clone(),
+        snapshot: snapshot.clone(),
+        verbose: verbose.clone(),
+        seen: seen.clone(),
     });
     server
         .register_event::<pumpkin::plugin::api::events::player::player_permission_check::PlayerPermissionCheckEvent, _>(
@@ -33,18 +65,85 @@ fn on_load(&mut self, server: Arc<Context>) -> Result<(), String> {
         )
         .await;
 
-    let tree = commands::build_command_tree(store);
+    server
+        .register_event::<pumpkin::plugin::api::events::player::player_join::PlayerJoinEvent, _>(
+            Arc::new(handler::SeedJoinHandler {
+                context: server.clone(),
+                store: store.clone(),
+            }),
+            pumpkin::plugin::EventPriority::Normal,
+            false,
+        )
+        .await;
+
+    server
+        .register_event::<pumpkin::plugin::api::events::player::player_leave::PlayerLeaveEvent, _>(
+            Arc::new(verbose::VerboseLeaveHandler(verbose.clone())),
+            pumpkin::plugin::EventPriority::Normal,
+            false,
+        )
+        .await;
+
+    if config.chat.enabled {
+        let chat = Arc::new(chat::SeedChatHandler {
+            context: server.clone(),
+            snapshot: snapshot.clone(),
+            format: config.chat.format,
+        });
+        server
+            .register_event::<pumpkin::plugin::api::events::player::player_chat::PlayerChatEvent, _>(
+                chat,
+                pumpkin::plugin::EventPriority::Normal,
+                true,
+            )
+            .await;
+    }
+
+    let backups = Arc::new(backup::Backups::new(&data_folder, config.backup.keep));
+    let tree = commands::build_command_tree(
+        store.clone(),
+        verbose,
+        backups.clone(),
+        snapshot.clone(),
+        seen,
+    );
     server.register_command(tree, "seed:admin").await;
 
+    let mut handles = vec![tasks::spawn_expiry_purge(store.clone(), server.clone())];
+    if config.storage.auto_reload {
+        match config.storage.backend {
+            config::Backend::Toml => {
+                handles.push(tasks::spawn_file_watcher(store.clone(), server.clone()));
+            }
+            config::Backend::Sqlite => {
+                server.log("storage.auto_reload only applies to the toml backend, ignoring it")
+            }
+        }
+    }
+    if config.backup.enabled {
+        handles.push(tasks::spawn_backups(
+            store,
+            backups,
+            Duration::from_secs(backup_interval),
+            server.clone(),
+        ));
+    }
+    unsafe { TASKS = Some(handles) };
+
     server.log("Seed v1.0.0 loaded!");
     Ok(())
 }
 
 #[plugin_method]
 fn on_unload(&mut self, server: Arc<Context>) -> Result<(), String> {
+    if let Some(tasks) = unsafe { TASKS.take() } {
+        for task in tasks {
+            task.abort();
+        }
+    }
     let store = unsafe { STORE.take() };
     if let Some(store) = store {
-        if let Err(e) = store.blocking_read().save() {
+        if let Err(e) = store.blocking_write().save() {
             server.log(format!("Failed to save on unload: {e}"));
         }
     }
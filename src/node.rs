@@ -0,0 +1,180 @@
+//! Permission node matching.
+//!
+//! Nodes look like `namespace:path.to.node`. A stored entry may end in a
+//! wildcard segment: `*` matches every node, `namespace:*` matches every node
+//! in that namespace and `namespace:path.*` matches every node below `path`.
+//!
+//! Entries may also carry context qualifiers in brackets, e.g.
+//! `minecraft:command.gamemode[dimension=minecraft:overworld,gamemode=creative]`.
+//! A qualified entry only applies when every qualifier matches the context of
+//! the check.
+
+/// Context qualifier keys understood in `[key=value]` suffixes.
+pub const CONTEXT_KEYS: [&str; 2] = ["dimension", "gamemode"];
+
+/// Where a permission check happens. Unknown parts never match a qualifier.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PermissionContext<'a> {
+    /// Dimension id, e.g. `minecraft:the_nether`.
+    pub dimension: Option<&'a str>,
+    /// Lowercase gamemode name, e.g. `creative`.
+    pub gamemode: Option<&'a str>,
+}
+
+/// Splits a stored entry into its node and whether it grants (`true`) or
+/// negates (`false`) it. Negated entries are written with a leading `-`.
+pub fn parse(entry: &str) -> (&str, bool) {
+    match entry.strip_prefix('-') {
+        Some(node) => (node, false),
+        None => (entry, true),
+    }
+}
+
+/// Splits `node[key=value,...]` into the node and its raw qualifiers.
+pub fn split_qualifiers(entry: &str) -> (&str, &str) {
+    match entry
+        .strip_suffix(']')
+        .and_then(|rest| rest.split_once('['))
+    {
+        Some((node, qualifiers)) => (node, qualifiers),
+        None => (entry, ""),
+    }
+}
+
+pub fn qualifier_pairs(qualifiers: &str) -> impl Iterator<Item = Option<(&str, &str)>> {
+    qualifiers
+        .split(',')
+        .filter(|q| !q.trim().is_empty())
+        .map(|q| q.split_once('=').map(|(k, v)| (k.trim(), v.trim())))
+}
+
+/// Whether every qualifier holds in `ctx`.
+pub fn qualifiers_apply(qualifiers: &str, ctx: &PermissionContext) -> bool {
+    qualifier_pairs(qualifiers).all(|pair| match pair {
+        Some(("dimension", value)) => ctx.dimension.is_some_and(|d| same_id(d, value)),
+        Some(("gamemode", value)) => ctx.gamemode.is_some_and(|g| g.eq_ignore_ascii_case(value)),
+        _ => false,
+    })
+}
+
+/// Compares ids with the `minecraft:` namespace being optional.
+fn same_id(a: &str, b: &str) -> bool {
+    a.strip_prefix("minecraft:").unwrap_or(a) == b.strip_prefix("minecraft:").unwrap_or(b)
+}
+
+/// Checks that an entry's qualifiers are well-formed, so typos are reported
+/// instead of silently producing an entry that never applies.
+pub fn validate(entry: &str) -> Result<(), String> {
+    let (_, qualifiers) = split_qualifiers(parse(entry).0);
+    for pair in qualifier_pairs(qualifiers) {
+        match pair {
+            Some((key, value)) if CONTEXT_KEYS.contains(&key) && !value.is_empty() => {}
+            Some((key, _)) if !CONTEXT_KEYS.contains(&key) => {
+                return Err(format!(
+                    "Unknown context '{key}' in '{entry}', expected one of: {}",
+                    CONTEXT_KEYS.join(", ")
+                ));
+            }
+            _ => {
+                return Err(format!(
+                    "Malformed context in '{entry}', expected [key=value,...]"
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The prefix a wildcard pattern matches, e.g. `minecraft:command.` for
+/// `minecraft:command.*`. A `*` only counts as a wildcard on its own or after
+/// a `:` or `.`, so it always stands for whole segments.
+pub fn wildcard_prefix(pattern: &str) -> Option<&str> {
+    pattern
+        .strip_suffix('*')
+        .filter(|prefix| prefix.is_empty() || prefix.ends_with(':') || prefix.ends_with('.'))
+}
+
+/// Whether `pattern`, a node or a wildcard such as `minecraft:command.*`,
+/// matches `node` the way permission checks match them.
+pub fn covers(pattern: &str, node: &str) -> bool {
+    match wildcard_prefix(pattern) {
+        Some(prefix) => node.len() > prefix.len() && node.starts_with(prefix),
+        None => pattern == node,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OVERWORLD_CREATIVE: PermissionContext = PermissionContext {
+        dimension: Some("minecraft:overworld"),
+        gamemode: Some("creative"),
+    };
+
+    #[test]
+    fn qualifiers_apply_when_all_match() {
+        let cases = [
+            ("", true),
+            ("dimension=minecraft:overworld", true),
+            ("dimension=overworld", true),
+            ("dimension=the_nether", false),
+            ("gamemode=CREATIVE", true),
+            ("dimension=overworld,gamemode=creative", true),
+            ("dimension=overworld,gamemode=survival", false),
+            ("world=overworld", false),
+            ("gamemode", false),
+        ];
+        for (qualifiers, expected) in cases {
+            assert_eq!(
+                qualifiers_apply(qualifiers, &OVERWORLD_CREATIVE),
+                expected,
+                "{qualifiers}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_context_never_matches() {
+        let ctx = PermissionContext::default();
+        assert!(qualifiers_apply("", &ctx));
+        assert!(!qualifiers_apply("dimension=overworld", &ctx));
+        assert!(!qualifiers_apply("gamemode=creative", &ctx));
+    }
+
+    #[test]
+    fn validate_reports_bad_qualifiers() {
+        let cases = [
+            ("minecraft:command.fly", true),
+            ("-minecraft:command.fly[gamemode=creative]", true),
+            (
+                "minecraft:command.tp[dimension=the_nether, gamemode=survival]",
+                true,
+            ),
+            ("minecraft:command.fly[world=creative]", false),
+            ("minecraft:command.fly[gamemode]", false),
+            ("minecraft:command.fly[gamemode=]", false),
+        ];
+        for (entry, valid) in cases {
+            assert_eq!(validate(entry).is_ok(), valid, "{entry}");
+        }
+    }
+
+    #[test]
+    fn wildcards_cover_whole_segments() {
+        let cases = [
+            ("*", "minecraft:command.fly", true),
+            ("minecraft:*", "minecraft:command.fly", true),
+            ("minecraft:command.*", "minecraft:command.fly", true),
+            ("minecraft:command.*", "minecraft:command.", false),
+            ("minecraft:command.*", "minecraft:commands.fly", false),
+            ("minecraft:command*", "minecraft:commands", false),
+            ("minecraft:command*", "minecraft:command*", true),
+            ("minecraft:command.fly", "minecraft:command.fly", true),
+            ("minecraft:command.fly", "minecraft:command.flying", false),
+        ];
+        for (pattern, node, covered) in cases {
+            assert_eq!(covers(pattern, node), covered, "{pattern} {node}");
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! Paged, color-coded command output. Short fields head the first page, long
+//! lists are split across pages, and entries can carry hover text and a click
+//! action, e.g. opening a group's info or filling in the command that removes
+//! a permission.
+
+use std::ops::Range;
+
+use pumpkin_util::text::TextComponent;
+use pumpkin_util::text::click::ClickEvent;
+use pumpkin_util::text::color::NamedColor;
+use pumpkin_util::text::hover::HoverEvent;
+
+/// List entries shown per page.
+const PAGE_SIZE: usize = 10;
+
+const TITLE: NamedColor = NamedColor::Gold;
+const LABEL: NamedColor = NamedColor::Yellow;
+const PLAIN: NamedColor = NamedColor::White;
+const NONE: NamedColor = NamedColor::Gray;
+const LINK: NamedColor = NamedColor::Aqua;
+
+/// One piece of output, such as a permission or a group name.
+pub struct Entry {
+    text: String,
+    color: NamedColor,
+    hover: Option<String>,
+    click: Option<ClickEvent>,
+}
+
+impl Entry {
+    pub fn new(text: impl Into<String>, color: NamedColor) -> Self {
+        Self {
+            text: text.into(),
+            color,
+            hover: None,
+            click: None,
+        }
+    }
+
+    /// Plain text without any action.
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self::new(text, PLAIN)
+    }
+
+    /// A permission entry, green when it grants and red when it's negated.
+    pub fn permission(entry: impl Into<String>, granted: bool) -> Self {
+        let color = if granted {
+            NamedColor::Green
+        } else {
+            NamedColor::Red
+        };
+        Self::new(entry, color)
+    }
+
+    /// A group name that opens the group's info when clicked.
+    pub fn group(name: &str) -> Self {
+        Self::new(name, LINK)
+            .hover(format!("Show group '{name}'"))
+            .run(format!("/seed group info {name}"))
+    }
+
+    pub fn hover(mut self, text: impl Into<String>) -> Self {
+        self.hover = Some(text.into());
+        self
+    }
+
+    /// Runs `command` when clicked. Only for commands that don't change
+    /// anything; see `suggest`.
+    pub fn run(mut self, command: String) -> Self {
+        self.click = Some(ClickEvent::RunCommand {
+            command: command.into(),
+        });
+        self
+    }
+
+    /// Puts `command` in the chat box when clicked, so a stray click can't
+    /// change anything.
+    pub fn suggest(mut self, command: String) -> Self {
+        self.click = Some(ClickEvent::SuggestCommand {
+            command: command.into(),
+        });
+        self
+    }
+
+    /// Copies `value` to the clipboard when clicked.
+    pub fn copy(mut self, value: String) -> Self {
+        self.click = Some(ClickEvent::CopyToClipboard {
+            value: value.into(),
+        });
+        self
+    }
+
+    fn component(self) -> TextComponent {
+        let mut component = TextComponent::text(self.text).color_named(self.color);
+        if let Some(hover) = self.hover {
+            component = component.hover_event(HoverEvent::show_text(
+                TextComponent::text(hover).color_named(NONE),
+            ));
+        }
+        if let Some(click) = self.click {
+            component = component.click_event(click);
+        }
+        component
+    }
+}
+
+/// Output with one-line fields at the top of the first page, followed by
+/// sections listing one entry per line, `PAGE_SIZE` entries to a page.
+pub struct Paged {
+    title: String,
+    fields: Vec<(String, Vec<Entry>)>,
+    sections: Vec<(String, Vec<Entry>)>,
+}
+
+impl Paged {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            fields: Vec::new(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// A field on the first page, its entries separated by commas.
+    pub fn field(mut self, label: impl Into<String>, entries: Vec<Entry>) -> Self {
+        self.fields.push((label.into(), entries));
+        self
+    }
+
+    /// A list paged together with the other sections.
+    pub fn section(mut self, label: impl Into<String>, entries: Vec<Entry>) -> Self {
+        self.sections.push((label.into(), entries));
+        self
+    }
+
+    /// Renders page `page`, counting from 1; pages past the end show the last
+    /// one. Links to the neighbouring pages run `{command} {page}`.
+    pub fn render(self, page: usize, command: &str) -> TextComponent {
+        let counts: Vec<usize> = self
+            .sections
+            .iter()
+            .map(|(_, entries)| entries.len())
+            .collect();
+        let Layout { page, pages, shown } = layout(&counts, page);
+        let mut parts = Vec::new();
+
+        let title = if pages > 1 {
+            format!("{} (page {page}/{pages})", self.title)
+        } else {
+            self.title
+        };
+        parts.push(TextComponent::text(title).color_named(TITLE).bold());
+
+        if page == 1 {
+            for (label, entries) in self.fields {
+                parts.push(TextComponent::text(format!("\n  {label}: ")).color_named(LABEL));
+                if entries.is_empty() {
+                    parts.push(TextComponent::text("(none)").color_named(NONE));
+                }
+                for (i, entry) in entries.into_iter().enumerate() {
+                    if i > 0 {
+                        parts.push(TextComponent::text(", ").color_named(PLAIN));
+                    }
+                    parts.push(entry.component());
+                }
+            }
+        }
+
+        for ((label, entries), range) in self.sections.into_iter().zip(shown) {
+            let Some(range) = range else {
+                continue;
+            };
+            let count = entries.len();
+            if count == 0 {
+                parts.push(TextComponent::text(format!("\n  {label}: ")).color_named(LABEL));
+                parts.push(TextComponent::text("(none)").color_named(NONE));
+                continue;
+            }
+            let continued = if range.start > 0 { ", continued" } else { "" };
+            parts.push(
+                TextComponent::text(format!("\n  {label} ({count}{continued}):"))
+                    .color_named(LABEL),
+            );
+            for entry in entries.into_iter().skip(range.start).take(range.len()) {
+                parts.push(TextComponent::text("\n    "));
+                parts.push(entry.component());
+            }
+        }
+
+        if pages > 1 {
+            parts.push(TextComponent::text("\n"));
+            if page > 1 {
+                parts.push(navigation("« Previous", command, page - 1));
+            }
+            if page > 1 && page < pages {
+                parts.push(TextComponent::text("  "));
+            }
+            if page < pages {
+                parts.push(navigation("Next »", command, page + 1));
+            }
+        }
+
+        parts
+            .into_iter()
+            .fold(TextComponent::text(""), TextComponent::add_child)
+    }
+}
+
+/// Which entries a page shows, see `layout`.
+#[derive(Debug, PartialEq)]
+struct Layout {
+    /// The page shown, after clamping to the pages there are.
+    page: usize,
+    pages: usize,
+    /// For each section, the range of its entries on the page, or `None` if
+    /// none of it is. Empty sections get an empty range on the first page.
+    shown: Vec<Option<Range<usize>>>,
+}
+
+/// Lays out sections holding `counts` entries for page `page`. Sections are
+/// laid end to end and cut into pages, so a section may start on one page
+/// and continue on the next.
+fn layout(counts: &[usize], page: usize) -> Layout {
+    let total: usize = counts.iter().sum();
+    let pages = total.div_ceil(PAGE_SIZE).max(1);
+    let page = page.clamp(1, pages);
+    let (start, end) = ((page - 1) * PAGE_SIZE, page * PAGE_SIZE);
+    let mut offset = 0;
+    let shown = counts
+        .iter()
+        .map(|&count| {
+            let section_start = offset;
+            offset += count;
+            if count == 0 {
+                return (page == 1).then_some(0..0);
+            }
+            let (first, last) = (start.max(section_start), end.min(offset));
+            (first < last).then(|| first - section_start..last - section_start)
+        })
+        .collect();
+    Layout { page, pages, shown }
+}
+
+fn navigation(text: &str, command: &str, page: usize) -> TextComponent {
+    Entry::new(format!("[{text}]"), LINK)
+        .hover(format!("Go to page {page}"))
+        .run(format!("{command} {page}"))
+        .component()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_are_cut_into_pages() {
+        let counts = [4, 0, 21];
+        assert_eq!(
+            layout(&counts, 1),
+            Layout {
+                page: 1,
+                pages: 3,
+                shown: vec![Some(0..4), Some(0..0), Some(0..6)],
+            }
+        );
+        assert_eq!(layout(&counts, 2).shown, [None, None, Some(6..16)]);
+        assert_eq!(layout(&counts, 3).shown, [None, None, Some(16..21)]);
+    }
+
+    #[test]
+    fn full_pages_need_no_more() {
+        assert_eq!(layout(&[10], 1).pages, 1);
+        assert_eq!(layout(&[5, 5], 1).shown, [Some(0..5), Some(0..5)]);
+        assert_eq!(layout(&[11], 2).shown, [Some(10..11)]);
+    }
+
+    #[test]
+    fn out_of_range_pages_are_clamped() {
+        let counts = [25];
+        assert_eq!(layout(&counts, 0), layout(&counts, 1));
+        assert_eq!(
+            layout(&counts, 99),
+            Layout {
+                page: 3,
+                pages: 3,
+                shown: vec![Some(20..25)],
+            }
+        );
+    }
+
+    #[test]
+    fn empty_lists_make_one_page() {
+        assert_eq!(
+            layout(&[], 1),
+            Layout {
+                page: 1,
+                pages: 1,
+                shown: vec![],
+            }
+        );
+        assert_eq!(
+            layout(&[0, 0], 5),
+            Layout {
+                page: 1,
+                pages: 1,
+                shown: vec![Some(0..0), Some(0..0)],
+            }
+        );
+    }
+}
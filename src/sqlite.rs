@@ -0,0 +1,164 @@
+//! SQLite storage backend. Each group, player and track is a row holding its
+//! TOML-serialized data, so only changed entries are written on save and the
+//! same serde defaults and migrations apply as with the TOML files.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use rusqlite::{Connection, Transaction, params};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use crate::storage::{Changes, Storage, StoredData};
+use crate::store::PermissionStore;
+
+/// Groups and tracks are keyed by name, players by UUID.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS groups (id TEXT PRIMARY KEY, data TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS players (id TEXT PRIMARY KEY, data TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS tracks (id TEXT PRIMARY KEY, data TEXT NOT NULL);
+";
+
+/// `PRAGMA user_version` of a database that has been set up, so an existing
+/// TOML setup is imported at most once.
+const INITIALIZED: i64 = 1;
+
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let connection = Connection::open(path)
+            .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+        connection
+            .execute_batch(SCHEMA)
+            .map_err(|e| format!("Failed to create tables: {e}"))?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Whether the database has been set up by `initialize`.
+    pub fn is_initialized(&self) -> Result<bool, String> {
+        let connection = self.connection.lock().map_err(|e| e.to_string())?;
+        let version: i64 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to read database version: {e}"))?;
+        Ok(version >= INITIALIZED)
+    }
+
+    /// Marks the database as set up, first writing everything in `import`,
+    /// e.g. when migrating from TOML. Both happen in one transaction.
+    pub fn initialize(&self, import: Option<&StoredData>) -> Result<(), String> {
+        self.write(|tx| {
+            if let Some(data) = import {
+                for (name, group) in data.groups.iter().flatten() {
+                    put(tx, "groups", name, Some(group))?;
+                }
+                for (uuid, pd) in &data.players {
+                    put(tx, "players", &uuid.to_string(), Some(pd))?;
+                }
+                for (name, track) in &data.tracks {
+                    put(tx, "tracks", name, Some(track))?;
+                }
+            }
+            tx.pragma_update(None, "user_version", INITIALIZED)
+                .map_err(|e| format!("Failed to record database version: {e}"))
+        })
+    }
+
+    /// Runs `write` in a transaction, so a save lands entirely or not at all.
+    fn write(&self, write: impl FnOnce(&Transaction) -> Result<(), String>) -> Result<(), String> {
+        let mut connection = self.connection.lock().map_err(|e| e.to_string())?;
+        let tx = connection
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {e}"))?;
+        write(&tx)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit changes: {e}"))
+    }
+}
+
+/// Inserts or replaces the row `id` in `table`, or deletes it if `value` is
+/// `None`.
+fn put<T: Serialize>(
+    tx: &Transaction,
+    table: &str,
+    id: &str,
+    value: Option<&T>,
+) -> Result<(), String> {
+    let result = match value {
+        Some(value) => {
+            let data =
+                toml::to_string(value).map_err(|e| format!("Failed to serialize '{id}': {e}"))?;
+            tx.execute(
+                &format!("INSERT OR REPLACE INTO {table} (id, data) VALUES (?1, ?2)"),
+                params![id, data],
+            )
+        }
+        None => tx.execute(&format!("DELETE FROM {table} WHERE id = ?1"), [id]),
+    };
+    result.map_err(|e| format!("Failed to write '{id}' to {table}: {e}"))?;
+    Ok(())
+}
+
+/// Reads every row of `table` as `(id, value)`.
+fn read_all<T: DeserializeOwned>(
+    connection: &Connection,
+    table: &str,
+) -> Result<Vec<(String, T)>, String> {
+    let mut statement = connection
+        .prepare(&format!("SELECT id, data FROM {table}"))
+        .map_err(|e| format!("Failed to read {table}: {e}"))?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| format!("Failed to read {table}: {e}"))?;
+    rows.map(|row| {
+        let (id, data) = row.map_err(|e| format!("Failed to read {table}: {e}"))?;
+        let value =
+            toml::from_str(&data).map_err(|e| format!("Failed to parse '{id}' in {table}: {e}"))?;
+        Ok((id, value))
+    })
+    .collect()
+}
+
+impl Storage for SqliteStorage {
+    fn load(&self) -> Result<StoredData, String> {
+        let connection = self.connection.lock().map_err(|e| e.to_string())?;
+        let groups: HashMap<_, _> = read_all(&connection, "groups")?.into_iter().collect();
+        let players = read_all(&connection, "players")?
+            .into_iter()
+            .map(|(uuid_str, data)| {
+                Uuid::parse_str(&uuid_str)
+                    .map(|uuid| (uuid, data))
+                    .map_err(|e| format!("Invalid UUID '{uuid_str}': {e}"))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        let tracks = read_all(&connection, "tracks")?.into_iter().collect();
+        Ok(StoredData {
+            groups: (!groups.is_empty()).then_some(groups),
+            players,
+            tracks,
+        })
+    }
+
+    fn save(&self, store: &PermissionStore, changes: &Changes) -> Result<(), String> {
+        self.write(|tx| {
+            for name in &changes.groups {
+                put(tx, "groups", name, store.groups.get(name))?;
+            }
+            for uuid in &changes.players {
+                put(tx, "players", &uuid.to_string(), store.players.get(uuid))?;
+            }
+            for name in &changes.tracks {
+                put(tx, "tracks", name, store.tracks.get(name))?;
+            }
+            Ok(())
+        })
+    }
+}
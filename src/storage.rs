@@ -0,0 +1,262 @@
+//! Where groups, players and tracks are persisted. The backend is chosen in
+//! `config.toml`; see `TomlStorage` and `SqliteStorage`.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::atomic;
+use crate::config::Backend;
+use crate::sqlite::SqliteStorage;
+use crate::store::{Group, PermissionStore, PlayerData, Track};
+
+/// Everything a backend has stored.
+pub struct StoredData {
+    /// `None` if no groups have been stored yet, i.e. on first run.
+    pub groups: Option<HashMap<String, Group>>,
+    pub players: HashMap<Uuid, PlayerData>,
+    pub tracks: HashMap<String, Track>,
+}
+
+/// What changed since the last save. A name or UUID that is no longer in the
+/// store was deleted.
+#[derive(Debug, Default)]
+pub struct Changes {
+    pub groups: HashSet<String>,
+    pub players: HashSet<Uuid>,
+    pub tracks: HashSet<String>,
+}
+
+impl Changes {
+    /// Every group, player and track in `store`, for rewriting it all.
+    pub fn everything(store: &PermissionStore) -> Self {
+        Self {
+            groups: store.groups.keys().cloned().collect(),
+            players: store.players.keys().copied().collect(),
+            tracks: store.tracks.keys().cloned().collect(),
+        }
+    }
+
+    pub fn merge(&mut self, other: Changes) {
+        self.groups.extend(other.groups);
+        self.players.extend(other.players);
+        self.tracks.extend(other.tracks);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty() && self.players.is_empty() && self.tracks.is_empty()
+    }
+}
+
+pub trait Storage: Send + Sync {
+    /// Reads everything stored. Never writes, so it is safe for dry runs.
+    fn load(&self) -> Result<StoredData, String>;
+
+    /// Finishes or discards a save interrupted by a crash, so the next `load`
+    /// sees one consistent save. Called before loading for real.
+    fn recover(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Whether `recover` has an interrupted save to finish.
+    fn interrupted_save(&self) -> bool {
+        false
+    }
+
+    /// Persists the entries named in `changes` as they are in `store`.
+    fn save(&self, store: &PermissionStore, changes: &Changes) -> Result<(), String>;
+}
+
+/// Opens the configured backend. The first time SQLite is used, an existing
+/// TOML setup in the same folder is imported into it. That is recorded in the
+/// database, so the TOML files, which are left in place, are never imported
+/// again, even if rows go missing later.
+pub fn open(backend: Backend, data_folder: &Path) -> Result<Arc<dyn Storage>, String> {
+    let toml = TomlStorage::new(data_folder.to_path_buf());
+    match backend {
+        Backend::Toml => Ok(Arc::new(toml)),
+        Backend::Sqlite => {
+            let sqlite = SqliteStorage::open(&data_folder.join("seed.db"))?;
+            if !sqlite.is_initialized()? {
+                toml.recover()?;
+                let import = Some(toml.load()?).filter(|data| data.groups.is_some());
+                sqlite.initialize(import.as_ref())?;
+            }
+            Ok(Arc::new(sqlite))
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct PlayersFile {
+    #[serde(default)]
+    players: HashMap<String, PlayerData>,
+}
+
+/// The files `TomlStorage` keeps its data in.
+pub const TOML_FILES: [&str; 3] = ["groups.toml", "players.toml", "tracks.toml"];
+
+/// The original layout: `groups.toml`, `players.toml` and `tracks.toml`.
+/// Each save rewrites only the files whose contents changed, all of them or
+/// none, see `atomic`.
+pub struct TomlStorage {
+    data_folder: PathBuf,
+}
+
+impl TomlStorage {
+    pub fn new(data_folder: PathBuf) -> Self {
+        Self { data_folder }
+    }
+
+    fn serialize_groups(groups: &HashMap<String, Group>) -> Result<String, String> {
+        toml::to_string_pretty(groups).map_err(|e| format!("Failed to serialize groups: {e}"))
+    }
+
+    fn serialize_players(players: &HashMap<Uuid, PlayerData>) -> Result<String, String> {
+        let file = PlayersFile {
+            players: players
+                .iter()
+                .map(|(uuid, data)| (uuid.to_string(), data.clone()))
+                .collect(),
+        };
+        toml::to_string_pretty(&file).map_err(|e| format!("Failed to serialize players: {e}"))
+    }
+
+    fn serialize_tracks(tracks: &HashMap<String, Track>) -> Result<String, String> {
+        toml::to_string_pretty(tracks).map_err(|e| format!("Failed to serialize tracks: {e}"))
+    }
+}
+
+impl Storage for TomlStorage {
+    fn load(&self) -> Result<StoredData, String> {
+        let groups_path = self.data_folder.join("groups.toml");
+        let players_path = self.data_folder.join("players.toml");
+        let tracks_path = self.data_folder.join("tracks.toml");
+
+        let groups = if groups_path.exists() {
+            let content = fs::read_to_string(&groups_path)
+                .map_err(|e| format!("Failed to read groups.toml: {e}"))?;
+            Some(
+                toml::from_str(&content)
+                    .map_err(|e| format!("Failed to parse groups.toml: {e}"))?,
+            )
+        } else {
+            None
+        };
+
+        let players = if players_path.exists() {
+            let content = fs::read_to_string(&players_path)
+                .map_err(|e| format!("Failed to read players.toml: {e}"))?;
+            let file: PlayersFile = toml::from_str(&content)
+                .map_err(|e| format!("Failed to parse players.toml: {e}"))?;
+            file.players
+                .into_iter()
+                .map(|(uuid_str, data)| {
+                    Uuid::parse_str(&uuid_str)
+                        .map(|uuid| (uuid, data))
+                        .map_err(|e| format!("Invalid UUID '{uuid_str}': {e}"))
+                })
+                .collect::<Result<HashMap<_, _>, _>>()?
+        } else {
+            HashMap::new()
+        };
+
+        let tracks = if tracks_path.exists() {
+            let content = fs::read_to_string(&tracks_path)
+                .map_err(|e| format!("Failed to read tracks.toml: {e}"))?;
+            toml::from_str(&content).map_err(|e| format!("Failed to parse tracks.toml: {e}"))?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(StoredData {
+            groups,
+            players,
+            tracks,
+        })
+    }
+
+    fn recover(&self) -> Result<(), String> {
+        atomic::recover(&self.data_folder, &TOML_FILES)
+    }
+
+    fn interrupted_save(&self) -> bool {
+        atomic::is_interrupted(&self.data_folder)
+    }
+
+    fn save(&self, store: &PermissionStore, changes: &Changes) -> Result<(), String> {
+        let mut files = Vec::new();
+        if !changes.groups.is_empty() {
+            files.push(("groups.toml", Self::serialize_groups(&store.groups)?));
+        }
+        if !changes.players.is_empty() {
+            files.push(("players.toml", Self::serialize_players(&store.players)?));
+        }
+        if !changes.tracks.is_empty() {
+            files.push(("tracks.toml", Self::serialize_tracks(&store.tracks)?));
+        }
+        if files.is_empty() {
+            return Ok(());
+        }
+        atomic::write_files(&self.data_folder, &files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("seed-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[test]
+    fn sqlite_imports_toml_only_once() {
+        let folder = temp_folder("import-once");
+        fs::write(
+            folder.join("groups.toml"),
+            "[default]\n[admin]\nweight = 10\n",
+        )
+        .unwrap();
+
+        let data = open(Backend::Sqlite, &folder).unwrap().load().unwrap();
+        assert_eq!(data.groups.unwrap().len(), 2);
+
+        // Losing the group rows must not bring the stale TOML data back.
+        let connection = rusqlite::Connection::open(folder.join("seed.db")).unwrap();
+        connection.execute("DELETE FROM groups", []).unwrap();
+        drop(connection);
+        let data = open(Backend::Sqlite, &folder).unwrap().load().unwrap();
+        assert!(data.groups.is_none());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn toml_load_leaves_interrupted_saves_to_recover() {
+        let folder = temp_folder("load-read-only");
+        fs::write(folder.join("groups.toml"), "[old]\n").unwrap();
+        fs::write(folder.join("groups.toml.tmp"), "[new]\n").unwrap();
+        fs::write(folder.join("save.commit"), "groups.toml").unwrap();
+        let storage = TomlStorage::new(folder.clone());
+
+        assert!(storage.interrupted_save());
+        let groups = storage.load().unwrap().groups.unwrap();
+        assert!(groups.contains_key("old"));
+        assert!(folder.join("groups.toml.tmp").exists());
+
+        storage.recover().unwrap();
+        assert!(!storage.interrupted_save());
+        let groups = storage.load().unwrap().groups.unwrap();
+        assert!(groups.contains_key("new"));
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -1,176 +1,1362 @@
-use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::fs;
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::PathBuf;
+use std::sync::Arc;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::cache::{EntryIndex, PlayerIndex, Snapshot};
+use crate::duration;
+use crate::node;
+use crate::storage::{Changes, Storage, StoredData};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Group {
     #[serde(default)]
     pub permissions: Vec<String>,
     #[serde(default)]
     pub inheritance: Vec<String>,
+    /// Decides between a player's groups: the heaviest group with an opinion
+    /// on a permission wins.
+    #[serde(default)]
+    pub weight: i32,
+    /// Free-form key/values such as `prefix`, `suffix` or `home-limit`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub meta: HashMap<String, String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlayerData {
     #[serde(default)]
     pub username: String,
-    #[serde(default = "default_group")]
-    pub group: String,
+    #[serde(
+        default = "default_groups",
+        alias = "group",
+        deserialize_with = "deserialize_groups"
+    )]
+    pub groups: Vec<String>,
     #[serde(default)]
     pub extra_permissions: Vec<String>,
     #[serde(default)]
     pub denied_permissions: Vec<String>,
+    /// Temporary extra permissions, mapped to their expiry (unix seconds).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub temp_permissions: HashMap<String, u64>,
+    /// Temporary denials, mapped to their expiry (unix seconds).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub temp_denied_permissions: HashMap<String, u64>,
+    /// Temporary group memberships, mapped to their expiry (unix seconds).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub temp_groups: HashMap<String, u64>,
+    /// Per-player meta, overriding anything set on their groups.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub meta: HashMap<String, String>,
+}
+
+impl PlayerData {
+    /// Extra permissions in effect at `now`, permanent and temporary.
+    pub fn active_extras(&self, now: u64) -> impl Iterator<Item = &String> {
+        self.extra_permissions
+            .iter()
+            .chain(unexpired(&self.temp_permissions, now))
+    }
+
+    /// Denials in effect at `now`, permanent and temporary.
+    pub fn active_denials(&self, now: u64) -> impl Iterator<Item = &String> {
+        self.denied_permissions
+            .iter()
+            .chain(unexpired(&self.temp_denied_permissions, now))
+    }
+
+    /// Group memberships in effect at `now`, permanent and temporary.
+    pub fn active_groups(&self, now: u64) -> impl Iterator<Item = &String> {
+        self.groups.iter().chain(unexpired(&self.temp_groups, now))
+    }
+
+    /// Drops temporary entries that expired at or before `now`. Returns
+    /// whether anything was removed.
+    pub fn purge_expired(&mut self, now: u64) -> bool {
+        let before = self.temp_permissions.len()
+            + self.temp_denied_permissions.len()
+            + self.temp_groups.len();
+        for entries in [
+            &mut self.temp_permissions,
+            &mut self.temp_denied_permissions,
+            &mut self.temp_groups,
+        ] {
+            entries.retain(|_, expires| *expires > now);
+        }
+        let after = self.temp_permissions.len()
+            + self.temp_denied_permissions.len()
+            + self.temp_groups.len();
+        after != before
+    }
+}
+
+fn unexpired(entries: &HashMap<String, u64>, now: u64) -> impl Iterator<Item = &String> {
+    entries
+        .iter()
+        .filter(move |(_, expires)| **expires > now)
+        .map(|(entry, _)| entry)
+}
+
+fn default_groups() -> Vec<String> {
+    vec!["default".to_string()]
 }
 
-fn default_group() -> String {
-    "default".to_string()
+/// Accepts both `groups = [...]` and the single `group = "..."` written by
+/// earlier versions, which is migrated to the list form on the next save.
+fn deserialize_groups<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(group) => vec![group],
+        OneOrMany::Many(groups) => groups,
+    })
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-struct PlayersFile {
+pub const META_PREFIX: &str = "prefix";
+pub const META_SUFFIX: &str = "suffix";
+
+/// A promotion ladder: an ordered list of groups a player moves along.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    /// Groups from the lowest rank to the highest.
     #[serde(default)]
-    players: HashMap<String, PlayerData>,
+    pub groups: Vec<String>,
+}
+
+/// What refers to a group, see `group_usage`.
+pub struct GroupUsage {
+    /// Players in the group, permanently or temporarily, sorted by username.
+    pub members: Vec<Uuid>,
+    /// Groups that inherit from it directly.
+    pub children: Vec<String>,
+    pub tracks: Vec<String>,
 }
 
 pub struct PermissionStore {
     pub data_folder: PathBuf,
     pub groups: HashMap<String, Group>,
     pub players: HashMap<Uuid, PlayerData>,
+    pub tracks: HashMap<String, Track>,
+    pub storage: Arc<dyn Storage>,
+    /// What permission checks read. Must be republished after mutating
+    /// `groups` or `players`, see `refresh_group` and `refresh_player`.
+    snapshot: Arc<ArcSwap<Snapshot>>,
+    /// Entries to write on the next `save`.
+    changes: Changes,
 }
 
 impl PermissionStore {
-    pub fn load(data_folder: PathBuf) -> Result<Self, String> {
-        fs::create_dir_all(&data_folder)
-            .map_err(|e| format!("Failed to create data folder: {e}"))?;
-
-        let groups_path = data_folder.join("groups.toml");
-        let players_path = data_folder.join("players.toml");
-
-        let groups = if groups_path.exists() {
-            let content = fs::read_to_string(&groups_path)
-                .map_err(|e| format!("Failed to read groups.toml: {e}"))?;
-            toml::from_str(&content).map_err(|e| format!("Failed to parse groups.toml: {e}"))?
-        } else {
-            HashMap::from([(
+    pub fn new(
+        data_folder: PathBuf,
+        storage: Arc<dyn Storage>,
+        groups: HashMap<String, Group>,
+        players: HashMap<Uuid, PlayerData>,
+        tracks: HashMap<String, Track>,
+    ) -> Self {
+        let store = Self {
+            data_folder,
+            groups,
+            players,
+            tracks,
+            storage,
+            snapshot: Arc::new(ArcSwap::from_pointee(Snapshot::default())),
+            changes: Changes::default(),
+        };
+        store.publish(|snapshot| {
+            for name in store.groups.keys() {
+                store.index_group(snapshot, name);
+            }
+            store.index_chains(snapshot);
+            for uuid in store.players.keys() {
+                store.index_player(snapshot, *uuid);
+            }
+        });
+        store
+    }
+
+    /// Loads the stored data on startup. If nothing at all is stored yet, this
+    /// is the first run and the store starts with a `default` group, written
+    /// on the next `save`. Never writes data by itself, apart from first
+    /// finishing a save interrupted by a crash, see `Storage::recover`.
+    pub fn load(data_folder: PathBuf, storage: Arc<dyn Storage>) -> Result<Self, String> {
+        storage.recover()?;
+        let data = storage.load()?;
+        if data.groups.is_none() && data.players.is_empty() && data.tracks.is_empty() {
+            let groups = HashMap::from([(
                 "default".to_string(),
                 Group {
                     permissions: vec![
                         "minecraft:command.help".to_string(),
                         "minecraft:command.list".to_string(),
                     ],
-                    inheritance: Vec::new(),
+                    ..Default::default()
                 },
-            )])
-        };
+            )]);
+            let mut store = Self::new(data_folder, storage, groups, HashMap::new(), HashMap::new());
+            store.changes.groups.insert("default".to_string());
+            return Ok(store);
+        }
+        Self::from_data(data_folder, storage, data)
+    }
 
-        let players = if players_path.exists() {
-            let content = fs::read_to_string(&players_path)
-                .map_err(|e| format!("Failed to read players.toml: {e}"))?;
-            let file: PlayersFile = toml::from_str(&content)
-                .map_err(|e| format!("Failed to parse players.toml: {e}"))?;
-            file.players
-                .into_iter()
-                .map(|(uuid_str, data)| {
-                    Uuid::parse_str(&uuid_str)
-                        .map(|uuid| (uuid, data))
-                        .map_err(|e| format!("Invalid UUID '{uuid_str}': {e}"))
-                })
-                .collect::<Result<HashMap<_, _>, _>>()?
-        } else {
-            HashMap::new()
-        };
+    /// Reads the stored data, e.g. to reload it. Unlike `load`, missing groups
+    /// are an error rather than a first run, so a deleted `groups.toml` can
+    /// never replace the groups in memory with defaults. Never writes, so
+    /// callers that go on to use the result should `Storage::recover` first.
+    pub fn read(data_folder: PathBuf, storage: Arc<dyn Storage>) -> Result<Self, String> {
+        let data = storage.load()?;
+        Self::from_data(data_folder, storage, data)
+    }
 
-        let store = Self {
+    fn from_data(
+        data_folder: PathBuf,
+        storage: Arc<dyn Storage>,
+        data: StoredData,
+    ) -> Result<Self, String> {
+        // Seed never leaves the store without groups, so none at all means the
+        // groups were deleted or truncated.
+        let groups = data
+            .groups
+            .filter(|groups| !groups.is_empty())
+            .ok_or("No groups are stored, so nothing was loaded. Restore groups.toml (or the groups table) from a backup, see /seed backup")?;
+        Ok(Self::new(
             data_folder,
+            storage,
             groups,
-            players,
+            data.players,
+            data.tracks,
+        ))
+    }
+
+    /// Writes the groups, players and tracks changed since the last save.
+    pub fn save(&mut self) -> Result<(), String> {
+        if self.changes.is_empty() {
+            return Ok(());
+        }
+        self.storage.save(self, &self.changes)?;
+        self.changes = Changes::default();
+        Ok(())
+    }
+
+    /// Writes everything, whether it changed or not.
+    pub fn save_all(&mut self) -> Result<(), String> {
+        self.storage.save(self, &Changes::everything(self))?;
+        self.changes = Changes::default();
+        Ok(())
+    }
+
+    /// Marks track `name` as created, deleted or changed, so the next `save`
+    /// writes it.
+    pub fn track_changed(&mut self, name: &str) {
+        self.changes.tracks.insert(name.to_string());
+    }
+
+    /// The current permission data, for checks that shouldn't hold the store.
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.snapshot.load_full()
+    }
+
+    /// A handle that always yields the latest snapshot, even across reloads.
+    pub fn snapshot_handle(&self) -> Arc<ArcSwap<Snapshot>> {
+        self.snapshot.clone()
+    }
+
+    /// Whether `other` holds the same groups, players and tracks.
+    pub fn same_contents(&self, other: &PermissionStore) -> bool {
+        self.groups == other.groups && self.players == other.players && self.tracks == other.tracks
+    }
+
+    /// Replaces the store's contents with `other`'s, e.g. on reload, and
+    /// publishes them to existing snapshot handles.
+    pub fn replace(&mut self, other: PermissionStore) {
+        let handle = self.snapshot_handle();
+        handle.store(other.snapshot());
+        *self = other;
+        self.snapshot = handle;
+    }
+
+    /// Replaces the store's contents with `data`, e.g. from a backup, and
+    /// writes it all. Groups, players and tracks missing from `data` are
+    /// deleted from storage.
+    pub fn restore(&mut self, data: StoredData) -> Result<(), String> {
+        let groups = data
+            .groups
+            .ok_or("Nothing to restore: no groups were stored")?;
+        let mut changes = Changes::everything(self);
+        let restored = Self::new(
+            self.data_folder.clone(),
+            self.storage.clone(),
+            groups,
+            data.players,
+            data.tracks,
+        );
+        self.replace(restored);
+        changes.merge(Changes::everything(self));
+        self.storage.save(self, &changes)?;
+        self.changes = Changes::default();
+        Ok(())
+    }
+
+    /// Records that group `name` was created, deleted or changed: checks are
+    /// brought up to date and the next `save` writes it. Every inheritance
+    /// chain is recomputed, since other groups may list it as a parent, and
+    /// its members are re-indexed in case its weight changed.
+    pub fn refresh_group(&mut self, name: &str) {
+        self.refresh(&[name], &[]);
+    }
+
+    /// Records that player `uuid` was created, deleted or changed: checks are
+    /// brought up to date and the next `save` writes it.
+    pub fn refresh_player(&mut self, uuid: Uuid) {
+        self.refresh(&[], &[uuid]);
+    }
+
+    /// `refresh_player` for several players, publishing a single snapshot
+    /// rather than one per player.
+    pub fn refresh_players(&mut self, uuids: impl IntoIterator<Item = Uuid>) {
+        let uuids: Vec<Uuid> = uuids.into_iter().collect();
+        self.refresh(&[], &uuids);
+    }
+
+    /// `refresh_group` and `refresh_player` for several groups and players in
+    /// a single snapshot. Edits spanning groups and players, like a rename,
+    /// must use this, or checks could see them half done, e.g. a player in a
+    /// group that isn't indexed yet.
+    pub fn refresh(&mut self, groups: &[&str], players: &[Uuid]) {
+        if groups.is_empty() && players.is_empty() {
+            return;
+        }
+        self.changes
+            .groups
+            .extend(groups.iter().map(|name| name.to_string()));
+        self.changes.players.extend(players);
+        self.publish(|snapshot| {
+            for name in groups {
+                self.index_group(snapshot, name);
+            }
+            let mut reindex: HashSet<Uuid> = players.iter().copied().collect();
+            if !groups.is_empty() {
+                self.index_chains(snapshot);
+                let members = snapshot.players.iter().filter(|(_, index)| {
+                    index
+                        .groups
+                        .iter()
+                        .any(|(group, _)| groups.contains(&group.as_str()))
+                });
+                reindex.extend(members.map(|(uuid, _)| *uuid));
+            }
+            for uuid in reindex {
+                self.index_player(snapshot, uuid);
+            }
+        });
+    }
+
+    /// Publishes a copy of the current snapshot with `update` applied. Writers
+    /// are serialized by the lock around the store, so none are lost.
+    fn publish(&self, update: impl FnOnce(&mut Snapshot)) {
+        let mut snapshot = Snapshot::clone(&self.snapshot.load());
+        update(&mut snapshot);
+        self.snapshot.store(Arc::new(snapshot));
+    }
+
+    fn index_group(&self, snapshot: &mut Snapshot, name: &str) {
+        if let Some(old) = snapshot.group_entries.remove(name) {
+            snapshot.count_nodes(&old, false);
+        }
+        let Some(group) = self.groups.get(name) else {
+            snapshot.group_meta.remove(name);
+            return;
         };
-        store.save()?;
-        Ok(store)
-    }
-
-    pub fn save(&self) -> Result<(), String> {
-        let groups_content = toml::to_string_pretty(&self.groups)
-            .map_err(|e| format!("Failed to serialize groups: {e}"))?;
-        fs::write(self.data_folder.join("groups.toml"), groups_content)
-            .map_err(|e| format!("Failed to write groups.toml: {e}"))?;
-
-        let file = PlayersFile {
-            players: self
-                .players
-                .iter()
-                .map(|(uuid, data)| (uuid.to_string(), data.clone()))
-                .collect(),
+        let mut entries = EntryIndex::default();
+        for entry in &group.permissions {
+            entries.insert(entry);
+        }
+        snapshot.count_nodes(&entries, true);
+        snapshot
+            .group_entries
+            .insert(name.to_string(), Arc::new(entries));
+        snapshot
+            .group_meta
+            .insert(name.to_string(), Arc::new(group.meta.clone()));
+    }
+
+    fn index_chains(&self, snapshot: &mut Snapshot) {
+        snapshot.group_chains = self
+            .groups
+            .keys()
+            .map(|name| {
+                let chain = self
+                    .group_chain(name)
+                    .into_iter()
+                    .map(String::from)
+                    .collect();
+                (name.clone(), chain)
+            })
+            .collect();
+    }
+
+    fn index_player(&self, snapshot: &mut Snapshot, uuid: Uuid) {
+        if let Some(old) = snapshot.players.remove(&uuid) {
+            snapshot.count_nodes(&old.entries, false);
+        }
+        let Some(pd) = self.players.get(&uuid) else {
+            return;
+        };
+        let mut index = PlayerIndex::default();
+        for entry in &pd.extra_permissions {
+            index.entries.insert_literal(entry, true, None);
+        }
+        for (entry, expires) in &pd.temp_permissions {
+            index.entries.insert_literal(entry, true, Some(*expires));
+        }
+        for entry in &pd.denied_permissions {
+            index.entries.insert_literal(entry, false, None);
+        }
+        for (entry, expires) in &pd.temp_denied_permissions {
+            index.entries.insert_literal(entry, false, Some(*expires));
+        }
+        index.groups = pd
+            .groups
+            .iter()
+            .map(|group| (group.clone(), None))
+            .chain(
+                pd.temp_groups
+                    .iter()
+                    .map(|(group, expires)| (group.clone(), Some(*expires))),
+            )
+            .collect();
+        sort_groups(&self.groups, &mut index.groups);
+        index.meta = pd.meta.clone();
+        snapshot.count_nodes(&index.entries, true);
+        snapshot.players.insert(uuid, Arc::new(index));
+    }
+
+    /// The player's groups, heaviest first. Players without data are treated
+    /// as members of `default`.
+    pub fn player_groups(&self, uuid: &Uuid) -> Vec<&str> {
+        match self.players.get(uuid) {
+            Some(pd) => self.sort_by_weight(pd.active_groups(duration::now())),
+            None => vec!["default"],
+        }
+    }
+
+    /// Orders group names by descending weight, breaking ties by name so the
+    /// result never depends on insertion order. Duplicates are dropped.
+    pub fn sort_by_weight<'a>(&self, names: impl IntoIterator<Item = &'a String>) -> Vec<&'a str> {
+        let mut sorted: Vec<_> = names.into_iter().map(String::as_str).collect();
+        if sorted.is_empty() {
+            sorted.push("default");
+        }
+        sorted.sort_by_key(|name| {
+            let weight = self.groups.get(*name).map_or(0, |g| g.weight);
+            (Reverse(weight), *name)
+        });
+        sorted.dedup();
+        sorted
+    }
+
+    /// The group followed by everything it inherits, nearest first. Parents are
+    /// walked breadth-first in `inheritance` order, so a group always takes
+    /// precedence over its ancestors and missing groups are skipped.
+    pub fn group_chain<'a>(&'a self, group_name: &'a str) -> Vec<&'a str> {
+        let mut chain = Vec::new();
+        let mut queue = VecDeque::from([group_name]);
+        while let Some(name) = queue.pop_front() {
+            if chain.contains(&name) {
+                continue;
+            }
+            let Some(group) = self.groups.get(name) else {
+                continue;
+            };
+            chain.push(name);
+            queue.extend(group.inheritance.iter().map(String::as_str));
+        }
+        chain
+    }
+
+    /// Renames group `old` to `new`, along with every reference to it: other
+    /// groups' parents, player memberships (permanent and temporary) and
+    /// tracks. Checks see the rename all at once, and the next `save` writes
+    /// all of it at once.
+    pub fn rename_group(&mut self, old: &str, new: &str) -> Result<(), String> {
+        if old == "default" {
+            return Err("Cannot rename the 'default' group".to_string());
+        }
+        if self.groups.contains_key(new) {
+            return Err(format!("Group '{new}' already exists"));
+        }
+        let Some(group) = self.groups.remove(old) else {
+            return Err(format!("Group '{old}' not found"));
         };
-        let players_content = toml::to_string_pretty(&file)
-            .map_err(|e| format!("Failed to serialize players: {e}"))?;
-        fs::write(self.data_folder.join("players.toml"), players_content)
-            .map_err(|e| format!("Failed to write players.toml: {e}"))?;
+        self.groups.insert(new.to_string(), group);
 
+        let children: Vec<String> = self
+            .groups
+            .iter()
+            .filter(|(_, group)| group.inheritance.iter().any(|p| p == old))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for child in &children {
+            for parent in &mut self.groups.get_mut(child).expect("just listed").inheritance {
+                if parent == old {
+                    *parent = new.to_string();
+                }
+            }
+        }
+
+        let members: Vec<Uuid> = self
+            .players
+            .iter()
+            .filter(|(_, pd)| {
+                pd.groups.iter().any(|g| g == old) || pd.temp_groups.contains_key(old)
+            })
+            .map(|(uuid, _)| *uuid)
+            .collect();
+        for uuid in &members {
+            let pd = self.players.get_mut(uuid).expect("just listed");
+            for group in &mut pd.groups {
+                if group == old {
+                    *group = new.to_string();
+                }
+            }
+            if let Some(expires) = pd.temp_groups.remove(old) {
+                pd.temp_groups.insert(new.to_string(), expires);
+            }
+        }
+        let mut groups = vec![old, new];
+        groups.extend(children.iter().map(String::as_str));
+        self.refresh(&groups, &members);
+
+        let tracks: Vec<String> = self
+            .tracks
+            .iter()
+            .filter(|(_, track)| track.groups.iter().any(|g| g == old))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in tracks {
+            for group in &mut self.tracks.get_mut(&name).expect("just listed").groups {
+                if group == old {
+                    *group = new.to_string();
+                }
+            }
+            self.track_changed(&name);
+        }
         Ok(())
     }
 
-    /// Resolve a permission for a player. Returns Some(true/false) if Seed has
-    /// an opinion, None to fall through to Pumpkin's default.
-    pub fn check_permission(&self, uuid: &Uuid, node: &str) -> Option<bool> {
-        let player_data = self.players.get(uuid);
-        let group_name = player_data.map(|p| p.group.as_str()).unwrap_or("default");
+    /// Players in group `name` at `now`, directly or through a group that
+    /// inherits from it, sorted by username. Each comes with the group that
+    /// puts them there, `name` itself for direct members.
+    pub fn group_members<'a>(
+        &'a self,
+        name: &'a str,
+        now: u64,
+    ) -> Vec<(&'a Uuid, &'a PlayerData, &'a str)> {
+        let descendants: Vec<&str> = self
+            .groups
+            .keys()
+            .map(String::as_str)
+            .filter(|group| self.group_chain(group).contains(&name))
+            .collect();
+        let mut members: Vec<(&Uuid, &PlayerData, &str)> = self
+            .players
+            .iter()
+            .filter_map(|(uuid, pd)| {
+                let mut groups = pd
+                    .active_groups(now)
+                    .filter(|g| descendants.contains(&g.as_str()));
+                let first = groups.next()?;
+                let direct = first == name || groups.any(|g| g == name);
+                Some((uuid, pd, if direct { name } else { first.as_str() }))
+            })
+            .collect();
+        members.sort_by(|a, b| a.1.username.cmp(&b.1.username));
+        members
+    }
 
-        if let Some(pd) = player_data {
-            // Denied overrides everything
-            if pd.denied_permissions.iter().any(|p| p == node) {
-                return Some(false);
+    /// Everything that refers to group `name`, sorted.
+    pub fn group_usage(&self, name: &str) -> GroupUsage {
+        let mut members: Vec<Uuid> = self
+            .players
+            .iter()
+            .filter(|(_, pd)| {
+                pd.groups.iter().any(|g| g == name) || pd.temp_groups.contains_key(name)
+            })
+            .map(|(uuid, _)| *uuid)
+            .collect();
+        members.sort_by(|a, b| self.players[a].username.cmp(&self.players[b].username));
+        let mut children: Vec<String> = self
+            .groups
+            .iter()
+            .filter(|(_, group)| group.inheritance.iter().any(|p| p == name))
+            .map(|(child, _)| child.clone())
+            .collect();
+        children.sort();
+        let mut tracks: Vec<String> = self
+            .tracks
+            .iter()
+            .filter(|(_, track)| track.groups.iter().any(|g| g == name))
+            .map(|(track, _)| track.clone())
+            .collect();
+        tracks.sort();
+        GroupUsage {
+            members,
+            children,
+            tracks,
+        }
+    }
+
+    /// Deletes group `name` and every reference to it. Its members are moved
+    /// to `fallback`, which is required if it has any; their temporary
+    /// memberships become temporary memberships of `fallback`. Child groups
+    /// and tracks just drop it. Checks see the deletion all at once. Returns
+    /// what was affected.
+    pub fn delete_group(
+        &mut self,
+        name: &str,
+        fallback: Option<&str>,
+    ) -> Result<GroupUsage, String> {
+        if name == "default" {
+            return Err("Cannot delete the 'default' group".to_string());
+        }
+        if !self.groups.contains_key(name) {
+            return Err(format!("Group '{name}' not found"));
+        }
+        if let Some(fallback) = fallback {
+            if fallback == name {
+                return Err(format!(
+                    "Members of '{name}' cannot fall back to '{name}' itself"
+                ));
             }
-            // Per-player extras override group
-            if pd.extra_permissions.iter().any(|p| p == node || p == "*") {
-                return Some(true);
+            if !self.groups.contains_key(fallback) {
+                return Err(format!("Group '{fallback}' not found"));
             }
         }
+        let usage = self.group_usage(name);
+        if !usage.members.is_empty() && fallback.is_none() {
+            return Err(format!(
+                "Group '{name}' has {} member(s); name a group to move them to",
+                usage.members.len()
+            ));
+        }
 
-        let group_perms = self.resolve_group_permissions(group_name);
-        if group_perms.contains(node) || group_perms.contains("*") {
-            return Some(true);
+        self.groups.remove(name);
+        for child in &usage.children {
+            let group = self.groups.get_mut(child).expect("listed in usage");
+            group.inheritance.retain(|p| p != name);
+        }
+        if let Some(fallback) = fallback {
+            for uuid in &usage.members {
+                let pd = self.players.get_mut(uuid).expect("listed in usage");
+                if let Some(pos) = pd.groups.iter().position(|g| g == name) {
+                    pd.groups.remove(pos);
+                    if !pd.groups.iter().any(|g| g == fallback) {
+                        pd.groups.insert(pos, fallback.to_string());
+                    }
+                    pd.temp_groups.remove(fallback);
+                }
+                if let Some(expires) = pd.temp_groups.remove(name)
+                    && !pd.groups.iter().any(|g| g == fallback)
+                {
+                    let current = pd
+                        .temp_groups
+                        .entry(fallback.to_string())
+                        .or_insert(expires);
+                    *current = (*current).max(expires);
+                }
+            }
+        }
+        let mut groups = vec![name];
+        groups.extend(usage.children.iter().map(String::as_str));
+        self.refresh(&groups, &usage.members);
+        for track in &usage.tracks {
+            let track_data = self.tracks.get_mut(track).expect("listed in usage");
+            track_data.groups.retain(|g| g != name);
+            self.track_changed(track);
         }
+        Ok(usage)
+    }
+
+    /// Creates group `new` as a copy of `source`'s permissions, parents,
+    /// weight and meta. Members are not copied.
+    pub fn clone_group(&mut self, source: &str, new: &str) -> Result<(), String> {
+        if self.groups.contains_key(new) {
+            return Err(format!("Group '{new}' already exists"));
+        }
+        let Some(group) = self.groups.get(source) else {
+            return Err(format!("Group '{source}' not found"));
+        };
+        self.groups.insert(new.to_string(), group.clone());
+        self.refresh_group(new);
+        Ok(())
+    }
 
-        None
+    /// Replaces the parents of group `name`, refusing parents that don't exist
+    /// and edits that would make a group inherit from itself. Parents that
+    /// are already listed are kept even if missing, so a broken entry can
+    /// still be removed.
+    pub fn set_parents(&mut self, name: &str, parents: Vec<String>) -> Result<(), String> {
+        let Some(group) = self.groups.get(name) else {
+            return Err(format!("Group '{name}' not found"));
+        };
+        for parent in &parents {
+            if parent == name {
+                return Err(format!("Group '{name}' cannot inherit from itself"));
+            }
+            if !self.groups.contains_key(parent) && !group.inheritance.contains(parent) {
+                return Err(format!("Group '{parent}' not found"));
+            }
+        }
+        let previous = std::mem::replace(
+            &mut self
+                .groups
+                .get_mut(name)
+                .expect("checked above")
+                .inheritance,
+            parents,
+        );
+        if let Some(cycle) = self.find_cycle(name) {
+            let cycle = cycle.join("' -> '");
+            self.groups
+                .get_mut(name)
+                .expect("checked above")
+                .inheritance = previous;
+            return Err(format!("That would create an inheritance cycle: '{cycle}'"));
+        }
+        self.refresh_group(name);
+        Ok(())
     }
 
-    pub fn resolve_group_permissions(&self, group_name: &str) -> HashSet<String> {
-        let mut result = HashSet::new();
+    /// An inheritance path from group `name` back to itself, if there is one,
+    /// e.g. `["admin", "mod", "admin"]`.
+    pub fn find_cycle<'a>(&'a self, name: &'a str) -> Option<Vec<&'a str>> {
+        let mut path = vec![name];
         let mut visited = HashSet::new();
-        self.collect_permissions(group_name, &mut result, &mut visited);
-        result
+        self.walk_to(name, name, &mut path, &mut visited)
+            .then_some(path)
     }
 
-    fn collect_permissions(
-        &self,
-        group_name: &str,
-        result: &mut HashSet<String>,
-        visited: &mut HashSet<String>,
-    ) {
-        if !visited.insert(group_name.to_string()) {
-            return;
+    /// Depth-first search from `from` for a parent link to `target`, leaving
+    /// the path taken in `path` if found.
+    fn walk_to<'a>(
+        &'a self,
+        from: &'a str,
+        target: &str,
+        path: &mut Vec<&'a str>,
+        visited: &mut HashSet<&'a str>,
+    ) -> bool {
+        let Some(group) = self.groups.get(from) else {
+            return false;
+        };
+        for parent in &group.inheritance {
+            path.push(parent);
+            if parent == target
+                || (visited.insert(parent) && self.walk_to(parent, target, path, visited))
+            {
+                return true;
+            }
+            path.pop();
         }
-        if let Some(group) = self.groups.get(group_name) {
-            for perm in &group.permissions {
-                result.insert(perm.clone());
+        false
+    }
+
+    /// Inheritance that can't be resolved as written: parents that don't
+    /// exist and cycles. Checks skip both, but they are almost always
+    /// mistakes in a hand edit.
+    pub fn inheritance_problems(&self) -> Vec<String> {
+        let mut names: Vec<&String> = self.groups.keys().collect();
+        names.sort();
+        let mut problems = Vec::new();
+        let mut in_cycle = HashSet::new();
+        for name in names {
+            for parent in &self.groups[name].inheritance {
+                if !self.groups.contains_key(parent) {
+                    problems.push(format!(
+                        "Group '{name}' inherits from missing group '{parent}'"
+                    ));
+                }
+            }
+            if in_cycle.contains(name.as_str()) {
+                continue;
+            }
+            if let Some(cycle) = self.find_cycle(name) {
+                problems.push(format!("Inheritance cycle: '{}'", cycle.join("' -> '")));
+                in_cycle.extend(cycle);
             }
-            for parent in &group.inheritance {
-                self.collect_permissions(parent, result, visited);
+        }
+        problems
+    }
+
+    /// Every entry a group gets through its chain, mapped to whether it is
+    /// granted. When an entry appears more than once, the nearest group in the
+    /// chain wins. Wildcards and qualifiers are listed as written rather than
+    /// weighed against each other; `Snapshot::trace_permission` shows what a
+    /// check actually decides.
+    pub fn resolve_group_permissions(&self, group_name: &str) -> HashMap<String, bool> {
+        let mut result = HashMap::new();
+        for name in self.group_chain(group_name) {
+            for entry in &self.groups[name].permissions {
+                let (node, granted) = node::parse(entry);
+                result.entry(node.to_string()).or_insert(granted);
             }
         }
+        result
+    }
+
+    /// Every entry a player gets across all their groups (heaviest first),
+    /// with the player's own extras and denials applied on top. Like
+    /// `resolve_group_permissions`, this lists entries, not check results.
+    pub fn resolve_player_permissions(&self, pd: &PlayerData) -> HashMap<String, bool> {
+        let now = duration::now();
+        let mut result = HashMap::new();
+        for group in self.sort_by_weight(pd.active_groups(now)) {
+            for (node, granted) in self.resolve_group_permissions(group) {
+                result.entry(node).or_insert(granted);
+            }
+        }
+        for ep in pd.active_extras(now) {
+            result.insert(ep.clone(), true);
+        }
+        for dp in pd.active_denials(now) {
+            result.insert(dp.clone(), false);
+        }
+        result
+    }
+
+    /// Resolves a meta value for a player. The player's own meta wins, then
+    /// their groups from heaviest to lightest, each walked nearest first.
+    pub fn get_meta(&self, uuid: &Uuid, key: &str) -> Option<&str> {
+        if let Some(value) = self.players.get(uuid).and_then(|pd| pd.meta.get(key)) {
+            return Some(value);
+        }
+        self.player_groups(uuid)
+            .into_iter()
+            .find_map(|group| self.get_group_meta(group, key))
+    }
+
+    /// Resolves a meta value for a group, falling back to its ancestors.
+    pub fn get_group_meta(&self, group_name: &str, key: &str) -> Option<&str> {
+        self.group_chain(group_name)
+            .into_iter()
+            .find_map(|name| self.groups[name].meta.get(key))
+            .map(String::as_str)
+    }
+
+    /// The player's chat prefix, i.e. the resolved `prefix` meta.
+    pub fn prefix(&self, uuid: &Uuid) -> Option<&str> {
+        self.get_meta(uuid, META_PREFIX)
+    }
+
+    /// The player's chat suffix, i.e. the resolved `suffix` meta.
+    pub fn suffix(&self, uuid: &Uuid) -> Option<&str> {
+        self.get_meta(uuid, META_SUFFIX)
+    }
+
+    /// Drops expired temporary entries from every player. Returns whether
+    /// anything was removed, i.e. whether the store needs saving.
+    pub fn purge_expired(&mut self) -> bool {
+        let now = duration::now();
+        let purged: Vec<Uuid> = self
+            .players
+            .iter_mut()
+            .filter_map(|(uuid, pd)| pd.purge_expired(now).then_some(*uuid))
+            .collect();
+        if purged.is_empty() {
+            return false;
+        }
+        self.refresh_players(purged);
+        true
+    }
+
+    /// Moves a player one group up a track, replacing their current group on
+    /// it. Players not on the track yet join its first group. Returns the group
+    /// left (if any) and the group joined.
+    pub fn promote(
+        &mut self,
+        uuid: Uuid,
+        username: &str,
+        track_name: &str,
+    ) -> Result<(Option<String>, String), String> {
+        let track = self.track_groups(track_name)?;
+        let from = track_position(&track, &self.current_groups(&uuid));
+        let to = from.map_or(0, |i| i + 1);
+        let Some(to) = track.get(to) else {
+            return Err(format!(
+                "{} is already at the top of track '{track_name}'",
+                display_name(&uuid, username)
+            ));
+        };
+        if !self.groups.contains_key(to) {
+            return Err(format!(
+                "Group '{to}' on track '{track_name}' does not exist"
+            ));
+        }
+        let from = from.map(|i| track[i].clone());
+        let pd = self.get_or_create_player(uuid, username);
+        pd.groups.retain(|g| Some(g) != from.as_ref() && g != to);
+        pd.groups.push(to.clone());
+        self.refresh_player(uuid);
+        Ok((from, to.clone()))
     }
 
+    /// Moves a player one group down a track. Returns the group left and the
+    /// group joined.
+    pub fn demote(
+        &mut self,
+        uuid: Uuid,
+        username: &str,
+        track_name: &str,
+    ) -> Result<(String, String), String> {
+        let track = self.track_groups(track_name)?;
+        let Some(from) = track_position(&track, &self.current_groups(&uuid)) else {
+            return Err(format!(
+                "{} is not on track '{track_name}'",
+                display_name(&uuid, username)
+            ));
+        };
+        let Some(to) = from.checked_sub(1).map(|i| &track[i]) else {
+            return Err(format!(
+                "{} is already at the bottom of track '{track_name}'",
+                display_name(&uuid, username)
+            ));
+        };
+        if !self.groups.contains_key(to) {
+            return Err(format!(
+                "Group '{to}' on track '{track_name}' does not exist"
+            ));
+        }
+        let from = &track[from];
+        let pd = self.get_or_create_player(uuid, username);
+        pd.groups.retain(|g| g != from && g != to);
+        pd.groups.push(to.clone());
+        self.refresh_player(uuid);
+        Ok((from.clone(), to.clone()))
+    }
+
+    fn track_groups(&self, track_name: &str) -> Result<Vec<String>, String> {
+        self.tracks
+            .get(track_name)
+            .map(|track| track.groups.clone())
+            .ok_or_else(|| format!("Track '{track_name}' not found"))
+    }
+
+    /// The player's permanent groups, `default` for players without data.
+    fn current_groups(&self, uuid: &Uuid) -> Vec<String> {
+        self.players
+            .get(uuid)
+            .map_or_else(default_groups, |pd| pd.groups.clone())
+    }
+
+    /// The player's data, created with the defaults if there is none yet.
+    /// Only call this when something is about to be added, so no-ops and
+    /// mistyped UUIDs don't leave empty records behind.
     pub fn get_or_create_player(&mut self, uuid: Uuid, username: &str) -> &mut PlayerData {
         let pd = self.players.entry(uuid).or_insert_with(|| PlayerData {
             username: username.to_string(),
-            group: "default".to_string(),
+            groups: default_groups(),
             extra_permissions: Vec::new(),
             denied_permissions: Vec::new(),
+            temp_permissions: HashMap::new(),
+            temp_denied_permissions: HashMap::new(),
+            temp_groups: HashMap::new(),
+            meta: HashMap::new(),
         });
-        pd.username = username.to_string();
+        if !username.is_empty() {
+            pd.username = username.to_string();
+        }
         pd
     }
+
+    /// Stores the name a player joined with, for players set up by UUID and
+    /// players who changed their name. Returns whether it changed.
+    pub fn record_username(&mut self, uuid: Uuid, username: &str) -> bool {
+        match self.players.get_mut(&uuid) {
+            Some(pd) if pd.username != username => {
+                pd.username = username.to_string();
+                self.changes.players.insert(uuid);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// How replies refer to a player: their username, or their UUID if they were
+/// set up by UUID and haven't joined since.
+pub fn display_name(uuid: &Uuid, username: &str) -> String {
+    if username.is_empty() {
+        uuid.to_string()
+    } else {
+        username.to_string()
+    }
+}
+
+/// Orders a player's cached groups like `sort_by_weight`. A permanent
+/// membership sorts before a temporary one in the same group, so dropping
+/// the duplicate keeps the longer-lived one.
+fn sort_groups(groups: &HashMap<String, Group>, list: &mut Vec<(String, Option<u64>)>) {
+    let weight = |name: &str| groups.get(name).map_or(0, |g| g.weight);
+    list.sort_by(|(a, a_expires), (b, b_expires)| {
+        (Reverse(weight(a)), a, a_expires.is_some()).cmp(&(
+            Reverse(weight(b)),
+            b,
+            b_expires.is_some(),
+        ))
+    });
+    list.dedup_by(|(b, _), (a, _)| a == b);
+}
+
+/// Index of the highest group on `track` that appears in `groups`.
+fn track_position(track: &[String], groups: &[String]) -> Option<usize> {
+    track.iter().rposition(|g| groups.contains(g))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::node::PermissionContext;
+
+    struct NoStorage;
+
+    impl Storage for NoStorage {
+        fn load(&self) -> Result<StoredData, String> {
+            Ok(StoredData {
+                groups: None,
+                players: HashMap::new(),
+                tracks: HashMap::new(),
+            })
+        }
+
+        fn save(&self, _: &PermissionStore, _: &Changes) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    fn store_with_track(groups: &[&str], track: &[&str]) -> PermissionStore {
+        let groups = groups
+            .iter()
+            .map(|name| (name.to_string(), Group::default()))
+            .collect();
+        let track = Track {
+            groups: track.iter().map(|g| g.to_string()).collect(),
+        };
+        let tracks = HashMap::from([("staff".to_string(), track)]);
+        PermissionStore::new(
+            PathBuf::new(),
+            Arc::new(NoStorage),
+            groups,
+            HashMap::new(),
+            tracks,
+        )
+    }
+
+    #[test]
+    fn failed_track_moves_leave_no_record() {
+        let uuid = Uuid::from_u128(1);
+        let mut store = store_with_track(&["default", "member"], &["member", "mod"]);
+        assert!(store.demote(uuid, "Steve", "staff").is_err());
+        assert!(store.players.is_empty());
+
+        store
+            .tracks
+            .get_mut("staff")
+            .unwrap()
+            .groups
+            .insert(0, "gone".to_string());
+        assert!(store.promote(uuid, "Steve", "staff").is_err());
+        assert!(store.players.is_empty());
+        assert!(store.changes.is_empty());
+    }
+
+    #[test]
+    fn promotion_creates_the_record() {
+        let uuid = Uuid::from_u128(1);
+        let mut store = store_with_track(&["default", "member"], &["member", "mod"]);
+        let moved = store.promote(uuid, "Steve", "staff").unwrap();
+        assert_eq!(moved, (None, "member".to_string()));
+        assert_eq!(store.players[&uuid].groups, ["default", "member"]);
+        assert!(store.changes.players.contains(&uuid));
+    }
+
+    #[test]
+    fn usernames_are_filled_in_on_join() {
+        let uuid = Uuid::from_u128(1);
+        let mut store = store_with_track(&["default", "member"], &["member"]);
+        // Set up by UUID before ever joining.
+        store
+            .get_or_create_player(uuid, "")
+            .groups
+            .push("member".to_string());
+        assert_eq!(store.players[&uuid].username, "");
+        assert_eq!(display_name(&uuid, ""), uuid.to_string());
+
+        assert!(store.record_username(uuid, "Steve"));
+        assert!(!store.record_username(uuid, "Steve"));
+        assert!(store.changes.players.contains(&uuid));
+        // Looking the player up by UUID again keeps the stored name.
+        store.get_or_create_player(uuid, "");
+        assert_eq!(store.players[&uuid].username, "Steve");
+        // Players without data aren't created just for joining.
+        assert!(!store.record_username(Uuid::from_u128(2), "Alex"));
+        assert_eq!(store.players.len(), 1);
+    }
+
+    #[test]
+    fn stored_nodes_follow_entry_changes() {
+        let uuid = Uuid::from_u128(1);
+        let mut store = store_with_track(&["default"], &[]);
+        let nodes = |store: &PermissionStore| -> Vec<String> {
+            let snapshot = store.snapshot();
+            snapshot
+                .nodes_with_prefix("minecraft:")
+                .map(String::from)
+                .collect()
+        };
+        store.groups.get_mut("default").unwrap().permissions = vec![
+            "-minecraft:command.kick[gamemode=creative]".to_string(),
+            "minecraft:*".to_string(),
+            "other:node".to_string(),
+        ];
+        store.refresh_group("default");
+        assert_eq!(nodes(&store), ["minecraft:*", "minecraft:command.kick"]);
+
+        let pd = store.get_or_create_player(uuid, "Steve");
+        pd.extra_permissions
+            .push("minecraft:command.kick".to_string());
+        store.refresh_player(uuid);
+        store.groups.get_mut("default").unwrap().permissions.clear();
+        store.refresh_group("default");
+        assert_eq!(nodes(&store), ["minecraft:command.kick"]);
+
+        store.players.remove(&uuid);
+        store.refresh_player(uuid);
+        assert!(nodes(&store).is_empty());
+    }
+
+    fn inherit(store: &mut PermissionStore, name: &str, parents: &[&str]) {
+        store.groups.get_mut(name).unwrap().inheritance =
+            parents.iter().map(|p| p.to_string()).collect();
+    }
+
+    #[test]
+    fn parents_must_exist_and_differ_from_the_group() {
+        let mut store = store_with_track(&["default", "member", "mod"], &[]);
+        let mut set = |name: &str, parents: &[&str]| {
+            let parents = parents.iter().map(|p| p.to_string()).collect();
+            store.set_parents(name, parents)
+        };
+        assert_eq!(
+            set("mod", &["mod"]).unwrap_err(),
+            "Group 'mod' cannot inherit from itself"
+        );
+        assert_eq!(
+            set("mod", &["ghost"]).unwrap_err(),
+            "Group 'ghost' not found"
+        );
+        assert_eq!(set("ghost", &[]).unwrap_err(), "Group 'ghost' not found");
+        assert!(set("mod", &["member", "default"]).is_ok());
+        assert_eq!(store.group_chain("mod"), ["mod", "member", "default"]);
+    }
+
+    #[test]
+    fn parents_that_would_form_a_cycle_are_refused() {
+        let mut store = store_with_track(&["default", "member", "mod"], &[]);
+        inherit(&mut store, "member", &["default"]);
+        inherit(&mut store, "mod", &["member"]);
+        assert_eq!(
+            store
+                .set_parents("default", vec!["mod".to_string()])
+                .unwrap_err(),
+            "That would create an inheritance cycle: 'default' -> 'mod' -> 'member' -> 'default'"
+        );
+        assert!(store.groups["default"].inheritance.is_empty());
+        assert!(store.inheritance_problems().is_empty());
+    }
+
+    #[test]
+    fn broken_inheritance_is_reported_and_can_be_fixed() {
+        let mut store = store_with_track(&["default", "a", "b"], &[]);
+        // Hand edits can leave a missing parent and a cycle behind.
+        inherit(&mut store, "default", &["ghost"]);
+        inherit(&mut store, "a", &["b"]);
+        inherit(&mut store, "b", &["a"]);
+        assert_eq!(
+            store.inheritance_problems(),
+            [
+                "Inheritance cycle: 'a' -> 'b' -> 'a'",
+                "Group 'default' inherits from missing group 'ghost'",
+            ]
+        );
+
+        // A missing parent that is already listed may stay while editing, but
+        // no new one can be added.
+        assert_eq!(
+            store
+                .set_parents("default", vec!["ghost".to_string(), "phantom".to_string()])
+                .unwrap_err(),
+            "Group 'phantom' not found"
+        );
+        store
+            .set_parents("default", vec!["ghost".to_string()])
+            .unwrap();
+        store.set_parents("default", Vec::new()).unwrap();
+        store.set_parents("b", Vec::new()).unwrap();
+        assert!(store.inheritance_problems().is_empty());
+    }
+
+    #[test]
+    fn checks_never_see_a_rename_half_done() {
+        let uuid = Uuid::from_u128(1);
+        let mut store = store_with_track(&["default", "base", "mod"], &[]);
+        store.groups.get_mut("base").unwrap().permissions = vec!["x:y".to_string()];
+        inherit(&mut store, "mod", &["base"]);
+        store.refresh_group("base");
+        store.refresh_group("mod");
+        store.get_or_create_player(uuid, "Steve").groups = vec!["mod".to_string()];
+        store.refresh_player(uuid);
+
+        let snapshot = store.snapshot_handle();
+        let done = AtomicBool::new(false);
+        let lost = std::thread::scope(|scope| {
+            let checker = scope.spawn(|| {
+                let ctx = PermissionContext::default();
+                let mut lost = 0;
+                while !done.load(Ordering::Relaxed) {
+                    if snapshot.load().check_permission(&uuid, "x:y", &ctx) != Some(true) {
+                        lost += 1;
+                    }
+                }
+                lost
+            });
+            // Renaming the player's group and its parent in turn.
+            for (old, new) in [("mod", "mod2"), ("base", "base2")].repeat(500) {
+                store.rename_group(old, new).unwrap();
+                store.rename_group(new, old).unwrap();
+            }
+            done.store(true, Ordering::Relaxed);
+            checker.join().unwrap()
+        });
+        assert_eq!(lost, 0);
+    }
+
+    #[test]
+    fn rename_rewrites_every_reference() {
+        let (steve, alex) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let mut store =
+            store_with_track(&["default", "member", "mod", "admin"], &["member", "mod"]);
+        inherit(&mut store, "admin", &["default", "mod"]);
+        store.get_or_create_player(steve, "Steve").groups =
+            vec!["default".to_string(), "mod".to_string()];
+        store
+            .get_or_create_player(alex, "Alex")
+            .temp_groups
+            .insert("mod".to_string(), 100);
+
+        store.rename_group("mod", "moderator").unwrap();
+        assert!(!store.groups.contains_key("mod"));
+        assert!(store.groups.contains_key("moderator"));
+        assert_eq!(store.groups["admin"].inheritance, ["default", "moderator"]);
+        assert_eq!(store.players[&steve].groups, ["default", "moderator"]);
+        assert_eq!(
+            store.players[&alex].temp_groups,
+            HashMap::from([("moderator".to_string(), 100)])
+        );
+        assert_eq!(store.tracks["staff"].groups, ["member", "moderator"]);
+        assert!(store.changes.players.contains(&steve));
+        assert!(store.changes.players.contains(&alex));
+        assert!(store.changes.tracks.contains("staff"));
+    }
+
+    #[test]
+    fn rename_refuses_default_and_taken_names() {
+        let mut store = store_with_track(&["default", "member", "mod"], &[]);
+        assert!(store.rename_group("default", "guest").is_err());
+        assert!(store.rename_group("member", "mod").is_err());
+        assert!(store.rename_group("ghost", "spirit").is_err());
+        assert!(store.groups.contains_key("default"));
+        assert!(store.groups.contains_key("member"));
+        assert!(store.changes.is_empty());
+    }
+
+    #[test]
+    fn clone_copies_the_group_but_not_its_members() {
+        let uuid = Uuid::from_u128(1);
+        let mut store = store_with_track(&["default", "mod"], &["mod"]);
+        let default = store.groups.get_mut("default").unwrap();
+        default.permissions = vec!["x:y".to_string()];
+        default.weight = 5;
+        inherit(&mut store, "mod", &["default"]);
+        store.get_or_create_player(uuid, "Steve");
+
+        store.clone_group("default", "guest").unwrap();
+        assert_eq!(store.groups["guest"], store.groups["default"]);
+        assert_eq!(store.players[&uuid].groups, ["default"]);
+        assert_eq!(store.groups["mod"].inheritance, ["default"]);
+        assert_eq!(store.tracks["staff"].groups, ["mod"]);
+        assert!(store.clone_group("default", "mod").is_err());
+        assert!(store.clone_group("ghost", "spirit").is_err());
+    }
+
+    #[test]
+    fn delete_moves_members_and_drops_references() {
+        let (steve, alex, herobrine) = (Uuid::from_u128(1), Uuid::from_u128(2), Uuid::from_u128(3));
+        let mut store = store_with_track(&["default", "member", "mod"], &["member", "mod"]);
+        inherit(&mut store, "mod", &["default", "member"]);
+        store.get_or_create_player(steve, "Steve").groups = vec!["member".to_string()];
+        // Temporarily in both; the longer membership carries over.
+        let alex_data = store.get_or_create_player(alex, "Alex");
+        alex_data.groups.clear();
+        alex_data.temp_groups =
+            HashMap::from([("member".to_string(), 200), ("default".to_string(), 100)]);
+        // Already permanently in the fallback, so nothing carries over.
+        store
+            .get_or_create_player(herobrine, "Herobrine")
+            .temp_groups
+            .insert("member".to_string(), 300);
+
+        let usage = store.delete_group("member", Some("default")).unwrap();
+        assert_eq!(usage.children, ["mod"]);
+        assert_eq!(usage.tracks, ["staff"]);
+        assert!(!store.groups.contains_key("member"));
+        assert_eq!(store.groups["mod"].inheritance, ["default"]);
+        assert_eq!(store.tracks["staff"].groups, ["mod"]);
+        assert_eq!(store.players[&steve].groups, ["default"]);
+        assert!(store.players[&alex].groups.is_empty());
+        assert_eq!(
+            store.players[&alex].temp_groups,
+            HashMap::from([("default".to_string(), 200)])
+        );
+        assert_eq!(store.players[&herobrine].groups, ["default"]);
+        assert!(store.players[&herobrine].temp_groups.is_empty());
+    }
+
+    #[test]
+    fn delete_needs_somewhere_for_members_to_go() {
+        let uuid = Uuid::from_u128(1);
+        let mut store = store_with_track(&["default", "member"], &["member"]);
+        store.get_or_create_player(uuid, "Steve").groups = vec!["member".to_string()];
+
+        assert!(store.delete_group("default", Some("member")).is_err());
+        assert!(store.delete_group("member", None).is_err());
+        assert!(store.delete_group("member", Some("member")).is_err());
+        assert!(store.delete_group("member", Some("ghost")).is_err());
+        assert!(store.groups.contains_key("member"));
+        assert_eq!(store.players[&uuid].groups, ["member"]);
+        assert_eq!(store.tracks["staff"].groups, ["member"]);
+
+        store.players.clear();
+        assert!(store.delete_group("member", None).is_ok());
+        assert!(store.tracks["staff"].groups.is_empty());
+    }
 }
@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use pumpkin::plugin::Context;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+use crate::backup::Backups;
+use crate::storage::TOML_FILES;
+use crate::store::PermissionStore;
+
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(30);
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Periodically drops expired temporary entries and saves the store when
+/// anything was removed. Checks already ignore expired entries, so this only
+/// keeps the files tidy.
+pub fn spawn_expiry_purge(
+    store: Arc<RwLock<PermissionStore>>,
+    server: Arc<Context>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+        loop {
+            interval.tick().await;
+            let mut store = store.write().await;
+            if store.purge_expired()
+                && let Err(e) = store.save()
+            {
+                server.log(format!("Failed to save after purging expired entries: {e}"));
+            }
+        }
+    })
+}
+
+/// Backs up the store every `interval`, starting right away.
+pub fn spawn_backups(
+    store: Arc<RwLock<PermissionStore>>,
+    backups: Arc<Backups>,
+    interval: Duration,
+    server: Arc<Context>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            let store = store.read().await;
+            if let Err(e) = backups.create(&store) {
+                server.log(format!("Failed to back up permission data: {e}"));
+            }
+        }
+    })
+}
+
+/// Modification time and size of each TOML data file, `None` if missing.
+type Fingerprint = Vec<Option<(SystemTime, u64)>>;
+
+fn fingerprint(data_folder: &Path) -> Fingerprint {
+    TOML_FILES
+        .iter()
+        .map(|name| {
+            let metadata = fs::metadata(data_folder.join(name)).ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        })
+        .collect()
+}
+
+/// Reloads the store when the TOML files change on disk. A change is acted on
+/// once the files have stayed the same for a whole `WATCH_INTERVAL`, so a
+/// burst of writes reloads once. If the files don't parse, the current data
+/// is kept. Files that hold exactly what is already loaded, such as those
+/// written by Seed's own saves, are ignored.
+pub fn spawn_file_watcher(
+    store: Arc<RwLock<PermissionStore>>,
+    server: Arc<Context>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let data_folder = store.read().await.data_folder.clone();
+        let mut seen = fingerprint(&data_folder);
+        let mut pending = false;
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let current = fingerprint(&data_folder);
+            if current != seen {
+                seen = current;
+                pending = true;
+                continue;
+            }
+            if !pending {
+                continue;
+            }
+            pending = false;
+
+            // Saves happen under the write lock, so this never reads a save
+            // that is halfway done.
+            let mut store = store.write().await;
+            let loaded = store
+                .storage
+                .recover()
+                .and_then(|()| PermissionStore::read(data_folder.clone(), store.storage.clone()));
+            match loaded {
+                Ok(loaded) if loaded.same_contents(&store) => {}
+                Ok(loaded) => {
+                    store.replace(loaded);
+                    server.log("Reloaded permission data after files changed on disk");
+                }
+                Err(e) => server.log(format!(
+                    "Files changed on disk but could not be loaded, keeping current data: {e}"
+                )),
+            }
+        }
+    })
+}
@@ -0,0 +1,203 @@
+//! Live logging of permission checks for `/seed verbose`.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use pumpkin::entity::player::Player;
+use pumpkin::plugin::api::events::player::player_leave::PlayerLeaveEvent;
+use pumpkin::plugin::{BoxFuture, Context, EventHandler};
+use pumpkin::server::Server;
+use pumpkin_util::text::TextComponent;
+use tokio::sync::Mutex;
+
+use crate::duration;
+
+/// Who receives a verbose session's output.
+#[derive(Clone)]
+pub enum Listener {
+    Console,
+    Player(Arc<Player>),
+}
+
+impl Listener {
+    fn is(&self, other: &Listener) -> bool {
+        match (self, other) {
+            (Listener::Console, Listener::Console) => true,
+            (Listener::Player(a), Listener::Player(b)) => a.gameprofile.id == b.gameprofile.id,
+            _ => false,
+        }
+    }
+}
+
+/// What a session watches. `None` fields match everything.
+pub struct Filter {
+    /// Username of the player whose checks are shown, compared ignoring case.
+    pub player: Option<String>,
+    /// Only checks whose node contains this text are shown.
+    pub node: Option<String>,
+}
+
+impl Filter {
+    fn matches(&self, player: &str, node: &str) -> bool {
+        self.player
+            .as_ref()
+            .is_none_or(|p| p.eq_ignore_ascii_case(player))
+            && self.node.as_ref().is_none_or(|n| node.contains(n.as_str()))
+    }
+}
+
+struct Session {
+    listener: Listener,
+    filter: Filter,
+    /// When recording, checks go to this file instead of the listener.
+    record: Option<(PathBuf, BufWriter<File>)>,
+}
+
+pub struct Verbose {
+    context: Arc<Context>,
+    data_folder: PathBuf,
+    /// Mirrors whether `sessions` is non-empty, so checks skip the lock while
+    /// nobody is listening.
+    active: AtomicBool,
+    sessions: Mutex<Vec<Session>>,
+}
+
+impl Verbose {
+    pub fn new(context: Arc<Context>, data_folder: PathBuf) -> Self {
+        Self {
+            context,
+            data_folder,
+            active: AtomicBool::new(false),
+            sessions: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Starts a session for `listener`, replacing any it already has. When
+    /// `record` is set, checks are written to a new file under `verbose/`,
+    /// whose path is returned.
+    pub async fn start(
+        &self,
+        listener: Listener,
+        filter: Filter,
+        record: bool,
+    ) -> Result<Option<PathBuf>, String> {
+        let record = if record {
+            let folder = self.data_folder.join("verbose");
+            fs::create_dir_all(&folder)
+                .map_err(|e| format!("Failed to create verbose folder: {e}"))?;
+            let now = duration::now();
+            let mut path = folder.join(format!("{now}.log"));
+            let mut n = 1;
+            while path.exists() {
+                n += 1;
+                path = folder.join(format!("{now}-{n}.log"));
+            }
+            let file = File::create(&path)
+                .map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+            Some((path, BufWriter::new(file)))
+        } else {
+            None
+        };
+        let path = record.as_ref().map(|(path, _)| path.clone());
+
+        let mut sessions = self.sessions.lock().await;
+        if let Some(index) = sessions.iter().position(|s| s.listener.is(&listener)) {
+            finish(sessions.swap_remove(index))?;
+        }
+        sessions.push(Session {
+            listener,
+            filter,
+            record,
+        });
+        self.active.store(true, Ordering::Relaxed);
+        Ok(path)
+    }
+
+    /// Ends `listener`'s session. Returns the recording's path, if it was
+    /// recording.
+    pub async fn stop(&self, listener: &Listener) -> Result<Option<PathBuf>, String> {
+        let mut sessions = self.sessions.lock().await;
+        let index = sessions
+            .iter()
+            .position(|s| s.listener.is(listener))
+            .ok_or("Verbose mode is not on")?;
+        let session = sessions.swap_remove(index);
+        self.active.store(!sessions.is_empty(), Ordering::Relaxed);
+        finish(session)
+    }
+
+    /// Ends the live session of a player who left. Recordings keep going and
+    /// can be stopped once they're back.
+    pub async fn forget(&self, player: &Player) {
+        let listener = player.gameprofile.id;
+        let mut sessions = self.sessions.lock().await;
+        sessions.retain(|s| {
+            s.record.is_some()
+                || !matches!(&s.listener, Listener::Player(p) if p.gameprofile.id == listener)
+        });
+        self.active.store(!sessions.is_empty(), Ordering::Relaxed);
+    }
+
+    /// Reports a permission check to every session whose filter matches.
+    /// `decided` is whether Seed set the result or it fell through to Pumpkin.
+    pub async fn log(&self, player: &Player, node: &str, result: bool, decided: bool) {
+        let name = &player.gameprofile.name;
+        let source = if decided { "Seed" } else { "fell through" };
+        let line = format!("{name} checked {node}: {result} ({source})");
+
+        let mut sessions = self.sessions.lock().await;
+        for session in sessions.iter_mut() {
+            if !session.filter.matches(name, node) {
+                continue;
+            }
+            if let Some((path, file)) = &mut session.record {
+                if let Err(e) = writeln!(file, "[{}] {line}", duration::now()) {
+                    self.context
+                        .log(format!("Failed to write {}: {e}", path.display()));
+                }
+                continue;
+            }
+            match &session.listener {
+                Listener::Console => self.context.log(format!("[verbose] {line}")),
+                Listener::Player(listener) => {
+                    listener
+                        .send_system_message(&TextComponent::text(format!("[verbose] {line}")))
+                        .await;
+                }
+            }
+        }
+    }
+}
+
+/// Flushes a session's recording, returning its path.
+fn finish(session: Session) -> Result<Option<PathBuf>, String> {
+    match session.record {
+        Some((path, mut file)) => {
+            file.flush()
+                .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+            Ok(Some(path))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Drops the verbose sessions of players who leave, so checks stop being
+/// sent to closed connections.
+pub struct VerboseLeaveHandler(pub Arc<Verbose>);
+
+impl EventHandler<PlayerLeaveEvent> for VerboseLeaveHandler {
+    fn handle<'a>(
+        &'a self,
+        _server: &'a Arc<Server>,
+        event: &'a PlayerLeaveEvent,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(async move { self.0.forget(&event.player).await })
+    }
+}